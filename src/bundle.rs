@@ -1,49 +1,68 @@
 use crate::embedded;
+use crate::error::SshpodError;
 use crate::kubectl::{self, RemoteTarget};
 use anyhow::{anyhow, bail, Context, Result};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use log::info;
+use sha2::{Digest, Sha256};
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::env;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
 use xz2::read::XzDecoder;
 
 pub const BUNDLE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+sshd1");
+// Unpacked sshd plus the compressed upload sitting next to it while it installs.
+pub const REQUIRED_DISK_MB: u64 = 32;
 
+/// Maps `uname -m` in the container to a bundle architecture such as `linux/amd64`.
 pub async fn detect_remote_arch(target: &RemoteTarget) -> Result<String> {
     let machine = kubectl::exec_capture_target(target, &["uname", "-m"])
         .await
         .context("failed to detect remote arch via uname -m")?;
-    let arch = match machine.trim() {
-        "x86_64" | "amd64" => "linux/amd64",
-        "aarch64" | "arm64" => "linux/arm64",
-        other => {
-            bail!("unsupported remote architecture: {}", other);
-        }
-    };
-    Ok(arch.to_string())
+    match map_machine_arch(machine.trim()) {
+        Some(arch) => Ok(arch.to_string()),
+        None => Err(SshpodError::ArchNotSupported(machine.trim().to_string()).into()),
+    }
 }
 
-pub async fn ensure_bundle(target: &RemoteTarget, base: &str, arch: &str) -> Result<()> {
-    let version_path = format!("{}/bundle/VERSION", base);
-    let arch_path = format!("{}/bundle/ARCH", base);
-    let remote_version =
-        kubectl::exec_capture_optional_target(target, &["cat", &version_path]).await?;
-    let remote_arch = kubectl::exec_capture_optional_target(target, &["cat", &arch_path]).await?;
+fn map_machine_arch(machine: &str) -> Option<&'static str> {
+    match machine {
+        "x86_64" | "amd64" => Some("linux/amd64"),
+        "aarch64" | "arm64" => Some("linux/arm64"),
+        "riscv64" => Some("linux/riscv64"),
+        "s390x" => Some("linux/s390x"),
+        "ppc64le" => Some("linux/ppc64le"),
+        _ => None,
+    }
+}
 
-    info!(
-        "[sshpod] checking bundle (remote version={:?}, remote arch={:?}, expected version={}, expected arch={})",
-        remote_version, remote_arch, BUNDLE_VERSION, arch
-    );
-    if remote_version.as_deref() == Some(BUNDLE_VERSION) && remote_arch.as_deref() == Some(arch) {
+/// Uploads the sshd bundle for `arch` under `base` unless the same version is already there.
+///
+/// Returns whether a bundle was installed.
+pub async fn ensure_bundle(target: &RemoteTarget, base: &str, arch: &str) -> Result<bool> {
+    if remote_bundle_current(target, base, arch).await? {
         info!("[sshpod] bundle already up to date");
-        return Ok(());
+        return Ok(false);
     }
 
+    // Only load (and possibly read from disk) the bundle once a reinstall is needed.
     let bundle_data = load_bundle_data(arch).await?;
+    let expected = expected_checksum(arch)?;
+    let actual = unpacked_sha256(&bundle_data)?;
+    if actual != expected {
+        bail!(
+            "sshd bundle for {} does not match its published checksum (expected {}, got {}); rebuild or download it again",
+            arch,
+            expected,
+            actual
+        );
+    }
 
     let meta = format!(
         "printf '%s\\n' \"{BUNDLE_VERSION}\" > \"{base}/bundle/VERSION\"; \
@@ -51,48 +70,161 @@ pub async fn ensure_bundle(target: &RemoteTarget, base: &str, arch: &str) -> Res
          chmod 600 \"{base}/bundle/VERSION\" \"{base}/bundle/ARCH\";"
     );
 
-    let install_xz = format!(
-        "set -eu; umask 077; mkdir -p \"{base}/bundle\"; chmod 700 \"{base}\" \"{base}/bundle\"; \
-         xz -dc > \"{base}/bundle/sshd\"; chmod 700 \"{base}/bundle/sshd\"; {meta}"
+    let commands = InstallCommands {
+        xz: install_script(base, "xz -dc", &meta),
+        gzip: install_script(base, "gzip -dc", &meta),
+        zstd: install_script(base, "zstd -dc", &meta),
+        lz4: install_script(base, "lz4 -dc", &meta),
+        plain: install_script(base, "cat", &meta),
+    };
+    let mut sshd_data: Option<Vec<u8>> = None;
+
+    install_with_fallbacks(target, base, &bundle_data, &mut sshd_data, &commands).await?;
+    info!("[sshpod] bundle install completed");
+
+    verify_remote_checksum(target, base, &expected).await?;
+    Ok(true)
+}
+
+/// Removes directories under `base_parent` whose bundle VERSION differs from this
+/// sshpod's, unless their sshd is still running.
+pub async fn ensure_bundle_cleanup_old(target: &RemoteTarget, base_parent: &str) -> Result<()> {
+    let script = format!(
+        "for dir in \"{base_parent}\"/*/; do \
+           dir=\"${{dir%/}}\"; \
+           [ -f \"$dir/bundle/VERSION\" ] || continue; \
+           [ \"$(cat \"$dir/bundle/VERSION\")\" = \"{BUNDLE_VERSION}\" ] && continue; \
+           if [ -f \"$dir/sshd.pid\" ] && kill -0 \"$(cat \"$dir/sshd.pid\")\" 2>/dev/null; then continue; fi; \
+           rm -rf \"$dir\" && echo \"$dir\"; \
+         done"
     );
-    let install_gz = format!(
-        "set -eu; umask 077; mkdir -p \"{base}/bundle\"; chmod 700 \"{base}\" \"{base}/bundle\"; \
-         gzip -dc > \"{base}/bundle/sshd\"; chmod 700 \"{base}/bundle/sshd\"; {meta}"
+    let removed = kubectl::exec_capture_target(target, &["sh", "-c", &script])
+        .await
+        .with_context(|| format!("failed to clean up old bundles under {}", base_parent))?;
+    for dir in removed.lines() {
+        info!("[sshpod] removed outdated bundle directory {}", dir);
+    }
+    Ok(())
+}
+
+async fn remote_bundle_current(target: &RemoteTarget, base: &str, arch: &str) -> Result<bool> {
+    let version_path = format!("{}/bundle/VERSION", base);
+    let arch_path = format!("{}/bundle/ARCH", base);
+    let remote_version =
+        kubectl::exec_capture_optional_target(target, &["cat", &version_path]).await?;
+    let remote_arch = kubectl::exec_capture_optional_target(target, &["cat", &arch_path]).await?;
+
+    info!(
+        "[sshpod] checking bundle (remote version={:?}, remote arch={:?}, expected version={}, expected arch={})",
+        remote_version, remote_arch, BUNDLE_VERSION, arch
     );
-    let install_plain = format!(
+    Ok(remote_version.as_deref() == Some(BUNDLE_VERSION) && remote_arch.as_deref() == Some(arch))
+}
+
+struct InstallCommands {
+    xz: String,
+    gzip: String,
+    zstd: String,
+    lz4: String,
+    plain: String,
+}
+
+// Two phases so an interrupted install never leaves a partial sshd behind a
+// current VERSION: remove VERSION, write .sshd.new, rename it over sshd, then
+// write VERSION.
+fn install_script(base: &str, decompress: &str, meta: &str) -> String {
+    format!(
         "set -eu; umask 077; mkdir -p \"{base}/bundle\"; chmod 700 \"{base}\" \"{base}/bundle\"; \
-         cat > \"{base}/bundle/sshd\"; chmod 700 \"{base}/bundle/sshd\"; {meta}"
-    );
-    let mut sshd_data: Option<Vec<u8>> = None;
+         rm -f \"{base}/bundle/VERSION\"; trap 'rm -f \"{base}/bundle/.sshd.new\"' EXIT; \
+         {decompress} > \"{base}/bundle/.sshd.new\"; chmod 700 \"{base}/bundle/.sshd.new\"; \
+         mv -f \"{base}/bundle/.sshd.new\" \"{base}/bundle/sshd\"; {meta}"
+    )
+}
 
-    let xz_err = match try_install_xz(target, &bundle_data, &install_xz).await {
-        Ok(_) => {
-            info!("[sshpod] bundle install completed");
-            return Ok(());
-        }
+async fn install_with_fallbacks(
+    target: &RemoteTarget,
+    base: &str,
+    bundle_data: &[u8],
+    sshd_data: &mut Option<Vec<u8>>,
+    commands: &InstallCommands,
+) -> Result<()> {
+    let xz_err = match try_install_xz(target, bundle_data, &commands.xz).await {
+        Ok(_) => return Ok(()),
         Err(e) => e,
     };
 
-    let gzip_err = match try_install_gzip(target, &bundle_data, &install_gz, &mut sshd_data).await {
-        Ok(_) => {
-            info!("[sshpod] bundle install completed");
-            return Ok(());
-        }
+    let gzip_err = match try_install_gzip(target, bundle_data, &commands.gzip, sshd_data).await {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    let zstd_err = match try_install_zstd(target, bundle_data, &commands.zstd, sshd_data).await {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    let lz4_err = match try_install_lz4(target, bundle_data, &commands.lz4, sshd_data).await {
+        Ok(_) => return Ok(()),
         Err(e) => e,
     };
 
-    let sshd_data = ensure_plain_data(&bundle_data, &mut sshd_data)
+    let plain = ensure_plain_data(bundle_data, sshd_data)
         .context("failed to prepare sshd payload for plain install")?;
-    install_bundle_with_command(target, &install_plain, sshd_data, "plain")
+    install_bundle_with_command(target, &commands.plain, plain, "plain")
         .await
-        .with_context(|| {
-            format!(
-                "failed to install bundle into {} (xz: {}; gzip: {})",
-                base, xz_err, gzip_err
-            )
-        })?;
+        .map_err(|err| {
+            anyhow::Error::new(SshpodError::BundleInstallFailed {
+                method: "plain".to_string(),
+                cause: format!("{:#}", err),
+            })
+            .context(format!(
+                "failed to install bundle into {} (xz: {}; gzip: {}; zstd: {}; lz4: {})",
+                base, xz_err, gzip_err, zstd_err, lz4_err
+            ))
+        })
+}
 
-    info!("[sshpod] bundle install completed");
+async fn verify_remote_checksum(target: &RemoteTarget, base: &str, expected: &str) -> Result<()> {
+    if !tool_available(target, "sha256sum").await? {
+        info!("[sshpod] skipping bundle checksum verification (sha256sum not available)");
+        return Ok(());
+    }
+    let sshd_path = format!("{}/bundle/sshd", base);
+    let output = kubectl::exec_capture_target(target, &["sha256sum", &sshd_path])
+        .await
+        .context("failed to compute checksum of installed sshd")?;
+    let actual = output.split_whitespace().next().unwrap_or_default();
+    if actual != expected {
+        let version_path = format!("{}/bundle/VERSION", base);
+        let _ = kubectl::exec_capture_optional_target(target, &["rm", "-f", &version_path]).await;
+        bail!(
+            "installed sshd checksum mismatch in {} (expected {}, got {})",
+            sshd_path,
+            expected,
+            actual
+        );
+    }
+    info!("[sshpod] bundle checksum verified ({})", expected);
+    Ok(())
+}
+
+#[cfg(test)]
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn cleanup_remote(target: &RemoteTarget, base: &str) -> Result<()> {
+    let script = format!(
+        "if [ -f \"{base}/sshd.pid\" ]; then kill \"$(cat \"{base}/sshd.pid\")\" 2>/dev/null || true; fi; \
+         rm -rf \"{base}\""
+    );
+    kubectl::exec_capture_target(target, &["sh", "-c", &script])
+        .await
+        .with_context(|| format!("failed to remove {}", base))?;
     Ok(())
 }
 
@@ -110,6 +242,21 @@ async fn load_bundle_data(arch: &str) -> Result<Cow<'static, [u8]>> {
     }
 }
 
+pub fn bundle_source(arch: &str) -> Result<String> {
+    if embedded::get_bundle(arch).is_some() {
+        Ok("embedded".to_string())
+    } else {
+        let path = locate_bundle(arch).with_context(|| {
+            format!(
+                "no embedded bundle for {} (embedded: {})",
+                arch,
+                embedded::list_available_arches().join(", ")
+            )
+        })?;
+        Ok(path.display().to_string())
+    }
+}
+
 async fn tool_available(target: &RemoteTarget, tool: &str) -> Result<bool> {
     Ok(kubectl::exec_capture_optional_target(
         target,
@@ -129,12 +276,53 @@ fn ensure_plain_data<'a>(
     Ok(cache.as_ref().unwrap())
 }
 
+// Hash of the decompressed sshd, streamed so the binary is never held in memory.
+fn unpacked_sha256(bundle_data: &[u8]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut XzDecoder::new(bundle_data), &mut hasher)
+        .context("failed to decompress xz")?;
+    Ok(hex(&hasher.finalize()))
+}
+
+// The SHA-256 of the decompressed sshd published with the bundle: compiled in
+// for embedded bundles, `sshd_<arch>.sha256` next to a bundle file otherwise.
+fn expected_checksum(arch: &str) -> Result<String> {
+    if let Some(checksum) = embedded::get_checksum(arch) {
+        return Ok(checksum.trim().to_string());
+    }
+    let path = locate_bundle(arch)?.with_extension("sha256");
+    let checksum = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "failed to read {}; bundle files need their checksum file next to them",
+            path.display()
+        )
+    })?;
+    Ok(checksum
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
 fn gzip_payload(data: &[u8]) -> Result<Vec<u8>> {
     let mut gz = GzEncoder::new(Vec::new(), Compression::default());
     gz.write_all(data).context("failed to write gzip payload")?;
     gz.finish().context("failed to finalize gzip payload")
 }
 
+fn zstd_payload(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::encode_all(data, 0).context("failed to build zstd payload")
+}
+
+// The frame format, which is what the lz4 CLI reads; raw blocks are not.
+fn lz4_payload(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    encoder
+        .write_all(data)
+        .context("failed to write lz4 payload")?;
+    encoder.finish().context("failed to finalize lz4 payload")
+}
+
 async fn try_install_xz(
     target: &RemoteTarget,
     bundle_data: &[u8],
@@ -162,23 +350,97 @@ async fn try_install_gzip(
     install_bundle_with_command(target, install_cmd, &gz_data, "gzip").await
 }
 
+async fn try_install_zstd(
+    target: &RemoteTarget,
+    bundle_data: &[u8],
+    install_cmd: &str,
+    sshd_cache: &mut Option<Vec<u8>>,
+) -> Result<()> {
+    if !tool_available(target, "zstd").await? {
+        info!("[sshpod] skipping zstd install (zstd not available)");
+        return Err(anyhow!("zstd not available in container"));
+    }
+    let sshd_data_ref = ensure_plain_data(bundle_data, sshd_cache)?;
+    let zstd_data = zstd_payload(sshd_data_ref)?;
+    install_bundle_with_command(target, install_cmd, &zstd_data, "zstd").await
+}
+
+async fn try_install_lz4(
+    target: &RemoteTarget,
+    bundle_data: &[u8],
+    install_cmd: &str,
+    sshd_cache: &mut Option<Vec<u8>>,
+) -> Result<()> {
+    if !tool_available(target, "lz4").await? {
+        info!("[sshpod] skipping lz4 install (lz4 not available)");
+        return Err(anyhow!("lz4 not available in container"));
+    }
+    let sshd_data_ref = ensure_plain_data(bundle_data, sshd_cache)?;
+    let lz4_data = lz4_payload(sshd_data_ref)?;
+    install_bundle_with_command(target, install_cmd, &lz4_data, "lz4").await
+}
+
 async fn install_bundle_with_command(
     target: &RemoteTarget,
     install_cmd: &str,
     payload: &[u8],
     label: &str,
 ) -> Result<()> {
-    info!("[sshpod] installing bundle via {}", label);
-    kubectl::exec_with_input_target(target, &["sh", "-c", install_cmd], payload).await?;
+    info!(
+        "[sshpod] installing bundle via {} ({} bytes)",
+        label,
+        payload.len()
+    );
+    let reader = ProgressReader::new(payload, label);
+    kubectl::exec_with_input_target(target, &["sh", "-c", install_cmd], reader).await?;
     Ok(())
 }
 
+const PROGRESS_INTERVAL: usize = 512 * 1024;
+
+struct ProgressReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    next_report: usize,
+    label: &'a str,
+}
+
+impl<'a> ProgressReader<'a> {
+    fn new(data: &'a [u8], label: &'a str) -> Self {
+        Self {
+            data,
+            pos: 0,
+            next_report: PROGRESS_INTERVAL,
+            label,
+        }
+    }
+}
+
+impl AsyncRead for ProgressReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let remaining = &self.data[self.pos..];
+        let n = remaining.len().min(buf.remaining());
+        buf.put_slice(&remaining[..n]);
+        self.pos += n;
+        while self.pos >= self.next_report && self.next_report <= self.data.len() {
+            info!(
+                "[sshpod] {} upload progress: {} KB / {} KB",
+                self.label,
+                self.next_report / 1024,
+                self.data.len() / 1024
+            );
+            self.next_report += PROGRESS_INTERVAL;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
 fn locate_bundle(arch: &str) -> Result<PathBuf> {
-    let filename = match arch {
-        "linux/amd64" => "sshd_amd64.xz".to_string(),
-        "linux/arm64" => "sshd_arm64.xz".to_string(),
-        _ => format!("sshd_{}.xz", arch.replace('/', "_")),
-    };
+    let filename = bundle_filename(arch);
     let mut candidates = Vec::new();
     let mut seen = HashSet::new();
 
@@ -206,6 +468,13 @@ fn locate_bundle(arch: &str) -> Result<PathBuf> {
     );
 }
 
+fn bundle_filename(arch: &str) -> String {
+    match arch.strip_prefix("linux/") {
+        Some(name) => format!("sshd_{}.xz", name),
+        None => format!("sshd_{}.xz", arch.replace('/', "_")),
+    }
+}
+
 fn decompress_xz(data: &[u8]) -> Result<Vec<u8>> {
     let mut decoder = XzDecoder::new(data);
     let mut buf = Vec::new();
@@ -217,10 +486,15 @@ fn decompress_xz(data: &[u8]) -> Result<Vec<u8>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{decompress_xz, ensure_plain_data, gzip_payload, load_bundle_data};
+    use super::{
+        bundle_filename, decompress_xz, ensure_plain_data, expected_checksum, gzip_payload,
+        install_script, load_bundle_data, lz4_payload, map_machine_arch, sha256_hex,
+        unpacked_sha256, zstd_payload, ProgressReader, PROGRESS_INTERVAL,
+    };
     use flate2::read::GzDecoder;
     use std::io::{Read, Write};
     use std::{fs, path::PathBuf};
+    use tokio::io::AsyncReadExt;
     use tokio::runtime::Runtime;
     use xz2::write::XzEncoder;
 
@@ -233,6 +507,35 @@ mod tests {
         assert_eq!(out, b"hello world");
     }
 
+    #[test]
+    fn machine_arch_mappings() {
+        assert_eq!(map_machine_arch("x86_64"), Some("linux/amd64"));
+        assert_eq!(map_machine_arch("aarch64"), Some("linux/arm64"));
+        assert_eq!(map_machine_arch("riscv64"), Some("linux/riscv64"));
+        assert_eq!(map_machine_arch("s390x"), Some("linux/s390x"));
+        assert_eq!(map_machine_arch("ppc64le"), Some("linux/ppc64le"));
+        assert_eq!(map_machine_arch("i686"), None);
+    }
+
+    #[test]
+    fn bundle_filenames_per_arch() {
+        assert_eq!(bundle_filename("linux/amd64"), "sshd_amd64.xz");
+        assert_eq!(bundle_filename("linux/riscv64"), "sshd_riscv64.xz");
+        assert_eq!(bundle_filename("linux/s390x"), "sshd_s390x.xz");
+        assert_eq!(bundle_filename("linux/ppc64le"), "sshd_ppc64le.xz");
+        assert_eq!(bundle_filename("test"), "sshd_test.xz");
+    }
+
+    #[test]
+    fn decompress_fixture_matches_checksum() {
+        let data = fs::read("tests/data/hello.tar.xz").expect("read fixture");
+        let out = decompress_xz(&data).expect("decompress fixture");
+        assert_eq!(
+            sha256_hex(&out),
+            "a32826ad161414ebdd8b3ab0c346437284f32d8a9cd48dee9d718213c6ece577"
+        );
+    }
+
     #[test]
     fn ensure_plain_data_caches_decompression() {
         let mut encoder = XzEncoder::new(Vec::new(), 6);
@@ -249,6 +552,58 @@ mod tests {
         assert_eq!(first_ptr, second.as_ptr(), "cache should be reused");
     }
 
+    #[test]
+    fn unpacked_sha256_matches_plain_hash() {
+        let mut encoder = XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(b"hash me").unwrap();
+        let data = encoder.finish().unwrap();
+        assert_eq!(unpacked_sha256(&data).unwrap(), sha256_hex(b"hash me"));
+    }
+
+    #[test]
+    fn embedded_bundles_match_checksums() {
+        for arch in crate::embedded::list_available_arches() {
+            let data = crate::embedded::get_bundle(arch).unwrap();
+            assert_eq!(
+                unpacked_sha256(data).unwrap(),
+                expected_checksum(arch).unwrap(),
+                "{}",
+                arch
+            );
+        }
+    }
+
+    #[test]
+    fn install_script_keeps_old_sshd_on_failure() {
+        let base = std::env::temp_dir().join(format!("sshpod-install-{}", std::process::id()));
+        let base_str = base.to_str().unwrap();
+        let run = |decompress: &str| {
+            let meta = format!("echo v1 > \"{}/bundle/VERSION\";", base_str);
+            let script = install_script(base_str, decompress, &meta);
+            std::process::Command::new("sh")
+                .args(["-c", &script])
+                .status()
+                .unwrap()
+                .success()
+        };
+
+        assert!(run("echo new"));
+        assert_eq!(
+            fs::read_to_string(base.join("bundle/sshd")).unwrap(),
+            "new\n"
+        );
+        assert!(base.join("bundle/VERSION").exists());
+
+        assert!(!run("echo partial; false"));
+        let sshd = fs::read_to_string(base.join("bundle/sshd")).unwrap();
+        let leftovers = base.join("bundle/.sshd.new").exists();
+        let version = base.join("bundle/VERSION").exists();
+        let _ = fs::remove_dir_all(&base);
+        assert_eq!(sshd, "new\n");
+        assert!(!leftovers);
+        assert!(!version);
+    }
+
     #[test]
     fn gzip_payload_round_trip() {
         let gz = gzip_payload(b"ping").expect("gzip");
@@ -258,6 +613,35 @@ mod tests {
         assert_eq!(out.as_bytes(), b"ping");
     }
 
+    #[test]
+    fn lz4_payload_round_trip() {
+        let lz4 = lz4_payload(b"ping").expect("lz4");
+        let mut decoder = lz4_flex::frame::FrameDecoder::new(&lz4[..]);
+        let mut out = Vec::new();
+        decoder.read_to_end(&mut out).expect("unlz4");
+        assert_eq!(out, b"ping");
+    }
+
+    #[test]
+    fn zstd_payload_round_trip() {
+        let zst = zstd_payload(b"ping").expect("zstd");
+        let out = zstd::decode_all(&zst[..]).expect("unzstd");
+        assert_eq!(out, b"ping");
+    }
+
+    #[test]
+    fn progress_reader_passes_data_through() {
+        let rt = Runtime::new().unwrap();
+        let data: Vec<u8> = (0..PROGRESS_INTERVAL * 2 + 10)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let mut reader = ProgressReader::new(&data, "test");
+        let mut out = Vec::new();
+        rt.block_on(reader.read_to_end(&mut out)).expect("read");
+        assert_eq!(out, data);
+        assert_eq!(reader.next_report, PROGRESS_INTERVAL * 3);
+    }
+
     #[test]
     fn load_bundle_data_reads_filesystem() {
         let rt = Runtime::new().unwrap();
@@ -273,6 +657,19 @@ mod tests {
             .expect("load bundle data");
         assert_eq!(&*loaded, data.as_slice());
 
+        let err = expected_checksum("test").unwrap_err();
+        assert!(format!("{:#}", err).contains("sshd_test.sha256"));
+        fs::write(
+            "sshd_test.sha256",
+            format!("{}  sshd\n", sha256_hex(b"from file")),
+        )
+        .unwrap();
+        assert_eq!(
+            expected_checksum("test").unwrap(),
+            unpacked_sha256(&data).unwrap()
+        );
+
+        fs::remove_file("sshd_test.sha256").ok();
         fs::remove_file(&path).ok();
     }
 }
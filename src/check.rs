@@ -0,0 +1,178 @@
+use crate::bundle;
+use crate::cli::TargetArgs;
+use crate::hostspec;
+use crate::keys::{self, KeyType};
+use crate::kubectl::{self, KubectlOptions, RemoteTarget};
+use crate::proxy;
+use crate::resolve;
+use anyhow::{bail, Context, Result};
+
+#[derive(Default)]
+pub struct Report {
+    pub failed: usize,
+    pub output: String,
+}
+
+impl Report {
+    fn record<T>(
+        &mut self,
+        name: &str,
+        result: Result<T>,
+        detail: impl Fn(&T) -> String,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.output
+                    .push_str(&format!("[PASS] {}: {}\n", name, detail(&value)));
+                Some(value)
+            }
+            Err(err) => {
+                self.output
+                    .push_str(&format!("[FAIL] {}: {:#}\n", name, err));
+                self.failed += 1;
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, name: &str) {
+        self.output
+            .push_str(&format!("[SKIP] {}: an earlier check failed\n", name));
+    }
+}
+
+pub async fn run(args: TargetArgs) -> Result<()> {
+    proxy::init_logger("error");
+    let report = sweep(&args).await;
+    print!("{}", report.output);
+    if report.failed > 0 {
+        bail!("{} check(s) failed", report.failed);
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+pub async fn sweep(args: &TargetArgs) -> Report {
+    let mut report = Report::default();
+
+    let host = report.record("hostspec", proxy::parse_host(args), |host| {
+        host.to_hostname()
+    });
+    let opts = host
+        .as_ref()
+        .map(|host| proxy::kubectl_options(host, args))
+        .unwrap_or_default();
+
+    let kubectl_ok = report
+        .record("kubectl", kubectl::client_version(&opts).await, |v| {
+            format!("client {}", v)
+        })
+        .is_some();
+
+    let target = match host {
+        Some(host) if kubectl_ok => {
+            let context_ok = report
+                .record("context", check_context(&host, &opts).await, |c| c.clone())
+                .is_some();
+            if context_ok {
+                report.record(
+                    "target",
+                    resolve::resolve_remote_target_with_wait(&host, &opts, args.wait).await,
+                    |(target, _)| format!("pod {} container {}", target.pod, target.container),
+                )
+            } else {
+                report.skip("target");
+                None
+            }
+        }
+        _ => {
+            report.skip("context");
+            report.skip("target");
+            None
+        }
+    };
+
+    report.record("client key", check_client_key().await, |k| k.clone());
+
+    match target {
+        Some((target, pod_info)) => {
+            let exec_ok = report
+                .record(
+                    "exec",
+                    kubectl::exec_capture_target(&target, &["sh", "-c", "echo ok"]).await,
+                    |_| "container accepts kubectl exec".to_string(),
+                )
+                .is_some();
+            let arch = if exec_ok {
+                report.record("arch", bundle::detect_remote_arch(&target).await, |a| {
+                    a.clone()
+                })
+            } else {
+                report.skip("arch");
+                None
+            };
+            match arch {
+                Some(arch) => {
+                    let bundle = match proxy::target_base(args, &pod_info.uid, &target.container) {
+                        Ok(base) => check_bundle(&target, &base, &arch).await,
+                        Err(err) => Err(err),
+                    };
+                    report.record("bundle", bundle, |source| {
+                        format!("{} can be installed from {}", arch, source)
+                    });
+                }
+                None => report.skip("bundle"),
+            }
+        }
+        None => {
+            report.skip("exec");
+            report.skip("arch");
+            report.skip("bundle");
+        }
+    }
+
+    report
+}
+
+async fn check_context(host: &hostspec::HostSpec, opts: &KubectlOptions) -> Result<String> {
+    match &host.context {
+        Some(ctx) => {
+            kubectl::ensure_context_exists(ctx, opts).await?;
+            Ok(ctx.clone())
+        }
+        None => Ok("using the current context".to_string()),
+    }
+}
+
+async fn check_client_key() -> Result<String> {
+    let dir = keys::key_dir(None)?;
+    for key_type in [KeyType::Ed25519, KeyType::Rsa(4096)] {
+        let path = dir.join(key_type.client_key_name());
+        if path.exists() {
+            return Ok(path.display().to_string());
+        }
+    }
+    // Only spawning matters here; `ssh-keygen -?` itself exits non-zero.
+    tokio::process::Command::new("ssh-keygen")
+        .arg("-?")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .context("no client key yet and ssh-keygen is not available to create one")?;
+    Ok(format!(
+        "not created yet; ssh-keygen will create {} on first connect",
+        dir.join(KeyType::Ed25519.client_key_name()).display()
+    ))
+}
+
+async fn check_bundle(target: &RemoteTarget, base: &str, arch: &str) -> Result<String> {
+    let source = bundle::bundle_source(arch)?;
+    let parent = base.rsplit_once('/').map(|(p, _)| p).unwrap_or(base);
+    // The path goes in as an argument so the shell never parses it.
+    let script = "mkdir -p \"$1\" && test -w \"$1\"";
+    kubectl::exec_capture_target(target, &["sh", "-c", script, "sh", parent])
+        .await
+        .with_context(|| format!("{} is not writable in the container", parent))?;
+    Ok(source)
+}
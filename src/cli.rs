@@ -1,14 +1,32 @@
-use crate::{install, proxy};
+//! The `sshpod` command line.
+
+use crate::hostspec::{self, HostSpecError};
+use crate::keys::{self, KeyType};
+use crate::selector::PodSelection;
+use crate::{check, config, copy, diagnose, exec, install, kubectl, list, proxy};
 use anyhow::{anyhow, Result};
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use clap_complete::{CompleteEnv, Shell};
+use std::ffi::OsStr;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
     name = "sshpod",
     version,
-    about = "ProxyCommand helper for ssh/scp/sftp to Kubernetes Pods"
+    about = "ProxyCommand helper for ssh/scp/sftp to Kubernetes Pods",
+    after_help = "Settings are taken from, in order of precedence: command-line flags, \
+SSHPOD_* environment variables (SSHPOD_LOG_LEVEL, SSHPOD_CONTEXT, SSHPOD_NAMESPACE, \
+SSHPOD_KUBECTL_RETRIES, SSHPOD_SSHD_TIMEOUT), ~/.config/sshpod/config.toml, built-in defaults."
 )]
-pub struct Cli {
+struct Cli {
+    /// kubectl-compatible binary to run (default: kubectl, or oc if kubectl is not on PATH)
+    #[arg(long, global = true, value_name = "PATH")]
+    kubectl_bin: Option<PathBuf>,
+    /// Query the Kubernetes API directly for lookups instead of spawning kubectl for each
+    #[arg(long, global = true)]
+    use_kube_api: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -18,30 +36,329 @@ enum Commands {
     /// ProxyCommand entry point
     Proxy(ProxyArgs),
     /// Update ~/.ssh/config with the sshpod ProxyCommand block
-    Configure,
+    Configure(ConfigureArgs),
+    /// Remove the sshpod block from ~/.ssh/config
+    Unconfigure,
+    /// Stop sshd and remove the sshpod bundle and keys from a pod
+    Cleanup(TargetArgs),
+    /// List ready pods and workloads that can be used as hostspec targets
+    List(ListArgs),
+    /// Run pre-flight checks against a target and report what would fail
+    Check(TargetArgs),
+    /// Show whether sshd is running in a pod (exit 0 running, 1 stopped, 2 unreachable)
+    Status(TargetArgs),
+    /// Generate the client and/or host keys in ~/.cache/sshpod
+    Keygen(KeygenArgs),
+    /// Run a command in the target container without setting up sshd
+    Exec(ExecArgs),
+    /// Forward a local port to a port in the target pod until interrupted
+    Forward(ForwardArgs),
+    /// Copy files to or from the target container with kubectl cp (prefix pod paths with pod:)
+    Copy(CopyArgs),
+    /// Collect versions, cluster info, checks and sshd logs for bug reports
+    Diagnose(DiagnoseArgs),
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
+/// Flags of `sshpod proxy`; parse them with clap, e.g. by flattening into your own parser.
 #[derive(Args, Debug, Clone)]
 pub struct ProxyArgs {
-    /// Target host (e.g. api-xxxx.ns.sshpod)
+    /// Which pod to connect to and how to reach the cluster.
+    #[command(flatten)]
+    pub target: TargetArgs,
+    /// kubectl context to use, overriding a context-- segment in the host
     #[arg(long)]
-    pub host: String,
+    pub context: Option<String>,
+    /// Namespace of the target, overriding a namespace-- segment in the host
+    #[arg(long, short = 'n')]
+    pub namespace: Option<String>,
+    /// Container to connect to, overriding a container-- segment in the host
+    #[arg(long, short = 'c')]
+    pub container: Option<String>,
     /// SSH login user (defaults to local user)
     #[arg(long)]
     pub user: Option<String>,
     /// OpenSSH-supplied port (unused but accepted for compatibility)
     #[arg(long)]
     pub port: Option<u16>,
+    /// Client key type to generate and install: ed25519 or rsa (4096 bits)
+    #[arg(long, default_value = "ed25519", value_name = "TYPE")]
+    pub key_type: KeyType,
+    /// Keep a separate client key per kubectl context under ~/.cache/sshpod/<context>/
+    #[arg(long)]
+    pub per_context_key: bool,
+    /// Regenerate the client key once it is older than this many days (0 = never)
+    #[arg(long, default_value_t = config::get().key_max_age_days, value_name = "DAYS")]
+    pub key_max_age_days: u64,
+    /// Copy this existing private key (and its .pub) into ~/.cache/sshpod and use it
+    #[arg(long, value_name = "PATH")]
+    pub identity_file: Option<PathBuf>,
+    /// Also authorize this public key in the pod; repeat for several keys
+    #[arg(long = "authorized-key", value_name = "PATH")]
+    pub authorized_keys: Vec<PathBuf>,
+    /// Give up if preparing the pod and port-forward takes longer than this many seconds (0 = never)
+    #[arg(long = "connect-timeout", default_value_t = 120, value_name = "SECS")]
+    pub connect_timeout_secs: u64,
+    /// Wait this many seconds for sshd to start in the pod
+    #[arg(
+        long = "sshd-startup-timeout",
+        default_value_t = config::get().sshd_startup_timeout_secs,
+        value_name = "SECS"
+    )]
+    pub sshd_startup_timeout_secs: u64,
+    /// Restart kubectl port-forward up to this many times if it drops before the SSH session starts
+    #[arg(long, default_value_t = 3)]
+    pub reconnect_retries: u32,
+    /// Bind the port-forward to this local port instead of a random one
+    #[arg(long, value_name = "PORT")]
+    pub local_port: Option<u16>,
+    /// Cap the SSH traffic through the proxy at this many kilobits per second (both directions)
+    #[arg(long, value_name = "KBPS")]
+    pub max_bandwidth_kbps: Option<u64>,
+    /// Close the connection after this many seconds without traffic (0 = never)
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_name = "SECS",
+        conflicts_with = "max_bandwidth_kbps"
+    )]
+    pub idle_timeout_secs: u64,
+    /// Try a port derived from the pod UID first when starting sshd, so it is predictable across runs
+    #[arg(long)]
+    pub deterministic_port: bool,
+    /// Start sshd on exactly this port (1024-65535) and fail if it is taken
+    #[arg(
+        long,
+        value_name = "PORT",
+        value_parser = clap::value_parser!(u16).range(1024..),
+        conflicts_with = "deterministic_port"
+    )]
+    pub sshd_port: Option<u16>,
+    /// How to pick a pod when a workload has several: default, newest or oldest
+    #[arg(long, value_enum, default_value_t = PodSelection::Default, value_name = "STRATEGY")]
+    pub pod_selector: PodSelection,
+    /// Also append log output to this file
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// Set up the pod and port-forward, print the connection parameters and exit without relaying
+    #[arg(long, conflicts_with = "unix_socket")]
+    pub dry_run: bool,
+    /// Relay the session over this Unix socket instead of setting up the pod and kubectl port-forward
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = [
+            "context",
+            "namespace",
+            "container",
+            "user",
+            "port",
+            "key_type",
+            "per_context_key",
+            "key_max_age_days",
+            "identity_file",
+            "authorized_keys",
+            "connect_timeout_secs",
+            "sshd_startup_timeout_secs",
+            "reconnect_retries",
+            "local_port",
+            "max_bandwidth_kbps",
+            "idle_timeout_secs",
+            "deterministic_port",
+            "sshd_port",
+            "pod_selector",
+            "base_dir",
+            "kubeconfig",
+            "impersonate",
+            "kubectl_retries",
+            "wait",
+        ]
+    )]
+    pub unix_socket: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct ExecArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    /// Pass local stdin to the command (like kubectl exec -i)
+    #[arg(long)]
+    pub stdin: bool,
+    /// Command and arguments to run
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct ForwardArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    /// Port in the pod to forward to
+    #[arg(long, value_name = "PORT")]
+    pub remote_port: u16,
+    /// Local port to listen on (defaults to a random free port)
+    #[arg(long, value_name = "PORT")]
+    pub local_port: Option<u16>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct CopyArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    /// Source: a local path or glob, or pod:<path>
+    #[arg(value_name = "SRC")]
+    pub src: String,
+    /// Destination: pod:<path> when copying up, a local path when copying down
+    #[arg(value_name = "DEST")]
+    pub dest: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct DiagnoseArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+    /// Write the report to this file instead of stdout
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct KeygenArgs {
+    /// Generate the client key (id_ed25519)
+    #[arg(long)]
+    pub client: bool,
+    /// Generate the host key (ssh_host_ed25519_key)
+    #[arg(long)]
+    pub host: bool,
+    /// Overwrite keys that already exist
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct ConfigureArgs {
+    /// Print the resulting ~/.ssh/config instead of writing it
+    #[arg(long)]
+    pub dry_run: bool,
+    /// sshpod binary for ProxyCommand (default: this executable, then sshpod on PATH)
+    #[arg(long, value_name = "PATH")]
+    pub sshpod_path: Option<PathBuf>,
+    /// Write ForwardAgent no instead of yes
+    #[arg(long)]
+    pub no_forward_agent: bool,
+    /// ServerAliveInterval for the generated block
+    #[arg(long, value_name = "SECONDS", default_value_t = install::SERVER_ALIVE_INTERVAL)]
+    pub server_alive_interval: u32,
+    /// Extra IdentityFile tried before sshpod's own keys
+    #[arg(long, value_name = "PATH")]
+    pub identity_file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub(crate) struct ListArgs {
+    /// kubectl context to query (defaults to the current context)
+    #[arg(long)]
+    pub context: Option<String>,
+    /// Namespace to list (defaults to the context's namespace)
+    #[arg(long, short = 'n')]
+    pub namespace: Option<String>,
+    /// Only list pods matching this field selector, e.g. spec.nodeName=worker-1
+    #[arg(long, value_name = "SELECTOR", conflicts_with = "all_namespaces")]
+    pub field_selector: Option<String>,
+    /// List Ready pods in every namespace instead (workloads are not included)
+    #[arg(long, short = 'A', conflicts_with = "namespace")]
+    pub all_namespaces: bool,
+    /// Path to the kubeconfig file (defaults to kubectl's own resolution, e.g. KUBECONFIG)
+    #[arg(long, value_name = "PATH")]
+    pub kubeconfig: Option<PathBuf>,
+    /// Output format
+    #[arg(long, short = 'o', value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Flags shared by the subcommands that act on one pod.
+#[derive(Args, Debug, Clone)]
+pub struct TargetArgs {
+    /// Target host (e.g. api-xxxx.ns.sshpod)
+    #[arg(long, add = ArgValueCompleter::new(complete_host))]
+    pub host: String,
+    /// Path to the kubeconfig file (defaults to kubectl's own resolution, e.g. KUBECONFIG)
+    #[arg(long, value_name = "PATH")]
+    pub kubeconfig: Option<PathBuf>,
+    /// Impersonate this user or service account for kubectl calls
+    #[arg(long = "as", value_name = "USER")]
+    pub impersonate: Option<String>,
+    /// Retry transient kubectl API failures this many times with exponential backoff
+    #[arg(long, default_value_t = config::get().kubectl_retries)]
+    pub kubectl_retries: u32,
+    /// Wait up to this many seconds for the target pod to become Ready
+    #[arg(long, value_name = "SECONDS")]
+    pub wait: Option<u64>,
+    /// Directory in the container sshd is installed in, used as is (default: /tmp/sshpod/<pod-uid>/<container>)
+    #[arg(long, value_name = "PATH")]
+    pub base_dir: Option<String>,
     /// Log level: error, info, debug
-    #[arg(long, default_value = "info")]
+    #[arg(long, default_value_t = config::get().log_level.clone())]
     pub log_level: String,
 }
 
+/// Runs the `sshpod` command line: loads settings, parses arguments and dispatches.
 pub async fn run() -> Result<()> {
+    // Settings double as clap defaults, so they are loaded before parsing; a
+    // broken file or variable must not take --help and friends down with it.
+    let settings = config::load().and_then(|mut settings| {
+        config::apply_env(&mut settings)?;
+        Ok(settings)
+    });
+    let settings_error = match settings {
+        Ok(settings) => {
+            config::init(settings);
+            None
+        }
+        Err(err) => {
+            config::init(config::Config::default());
+            Some(err)
+        }
+    };
+    CompleteEnv::with_factory(Cli::command).complete();
     let cli = Cli::parse();
+    if let Some(err) = settings_error {
+        match &cli.command {
+            Some(command) if uses_settings(command) => return Err(err),
+            _ => eprintln!("warning: ignoring settings: {:#}", err),
+        }
+    }
+    kubectl::init_binary(cli.kubectl_bin);
+    kubectl::init_backend(cli.use_kube_api);
+    if let Some(command) = &cli.command {
+        preflight(command)?;
+    }
     match cli.command {
         Some(Commands::Proxy(args)) => proxy::run(args).await?,
-        Some(Commands::Configure) => install::run().await?,
+        Some(Commands::Configure(args)) => install::run(args).await?,
+        Some(Commands::Unconfigure) => install::uninstall().await?,
+        Some(Commands::Cleanup(args)) => proxy::cleanup(args).await?,
+        Some(Commands::List(args)) => list::run(args).await?,
+        Some(Commands::Check(args)) => check::run(args).await?,
+        Some(Commands::Status(args)) => proxy::status(args).await?,
+        Some(Commands::Keygen(args)) => keygen(args).await?,
+        Some(Commands::Exec(args)) => exec::run(args).await?,
+        Some(Commands::Forward(args)) => proxy::forward(args).await?,
+        Some(Commands::Copy(args)) => copy::run(args).await?,
+        Some(Commands::Diagnose(args)) => diagnose::run(args).await?,
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "sshpod", &mut std::io::stdout())
+        }
         None => {
             return Err(anyhow!(
                 "no command provided. Use the configure or proxy subcommands."
@@ -50,3 +367,122 @@ pub async fn run() -> Result<()> {
     }
     Ok(())
 }
+
+fn uses_settings(command: &Commands) -> bool {
+    !matches!(
+        command,
+        Commands::Configure(_)
+            | Commands::Unconfigure
+            | Commands::Keygen(_)
+            | Commands::Completions { .. }
+    )
+}
+
+// Catch missing tools up front instead of a bare "No such file or directory" on spawn.
+fn preflight(command: &Commands) -> Result<()> {
+    match command {
+        // Relaying over a socket needs neither kubectl nor keys.
+        Commands::Proxy(args) if args.unix_socket.is_some() => Ok(()),
+        Commands::Proxy(_) => {
+            kubectl::ensure_binary()?;
+            keys::ensure_ssh_keygen()
+        }
+        Commands::Keygen(_) => keys::ensure_ssh_keygen(),
+        Commands::Cleanup(_)
+        | Commands::List(_)
+        | Commands::Status(_)
+        | Commands::Exec(_)
+        | Commands::Forward(_)
+        | Commands::Copy(_) => kubectl::ensure_binary(),
+        // check and diagnose report missing tools themselves.
+        _ => Ok(()),
+    }
+}
+
+async fn keygen(args: KeygenArgs) -> Result<()> {
+    let both = !args.client && !args.host;
+    let mut names = Vec::new();
+    if args.client || both {
+        names.push(KeyType::Ed25519.client_key_name());
+    }
+    if args.host || both {
+        names.push("ssh_host_ed25519_key");
+    }
+
+    let dir = keys::key_dir(None)?;
+    for name in names {
+        let path = dir.join(name);
+        if path.exists() && !args.force {
+            eprintln!(
+                "warning: {} already exists; pass --force to overwrite it",
+                path.display()
+            );
+            continue;
+        }
+        keys::generate_key(name, KeyType::Ed25519).await?;
+        println!("{}", keys::fingerprint(&path).await?);
+    }
+    Ok(())
+}
+
+fn complete_host(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    // Only finished segments count; the one being typed is still partial.
+    let typed = hostspec::parse_lenient(current.rsplit_once('.').map_or("", |(done, _)| done));
+    if typed
+        .errors
+        .iter()
+        .any(|err| matches!(err, HostSpecError::MissingSeparator { .. }))
+    {
+        return Vec::new();
+    }
+    let handle = tokio::runtime::Handle::current();
+    let hosts = tokio::task::block_in_place(|| handle.block_on(list::hostnames(&typed)));
+    hosts
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|host| host.starts_with(current))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preflight, Cli};
+    use clap::{error::ErrorKind, CommandFactory, Parser};
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn unix_socket_rejects_pod_and_pump_flags() {
+        let base = ["sshpod", "proxy", "--host", "h", "--unix-socket", "/tmp/s"];
+        assert!(Cli::try_parse_from(base).is_ok());
+        for extra in [
+            ["--namespace", "ns"],
+            ["--max-bandwidth-kbps", "10"],
+            ["--idle-timeout-secs", "5"],
+            ["--kubectl-retries", "2"],
+        ] {
+            let kind = Cli::try_parse_from(base.iter().chain(extra.iter())).map(|_| ());
+            assert_eq!(
+                kind.map_err(|e| e.kind()),
+                Err(ErrorKind::ArgumentConflict),
+                "{:?}",
+                extra
+            );
+        }
+    }
+
+    #[test]
+    fn unix_socket_proxy_needs_no_tools() {
+        let cli = Cli::try_parse_from(["sshpod", "proxy", "--host", "h", "--unix-socket", "/s"])
+            .map_err(|e| e.to_string())
+            .unwrap();
+        assert!(preflight(cli.command.as_ref().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::paths;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub default_context: Option<String>,
+    pub default_namespace: Option<String>,
+    pub log_level: String,
+    pub key_max_age_days: u64,
+    pub kubectl_retries: u32,
+    pub sshd_startup_timeout_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            default_context: None,
+            default_namespace: None,
+            log_level: "info".to_string(),
+            key_max_age_days: 0,
+            kubectl_retries: 0,
+            sshd_startup_timeout_secs: 40,
+        }
+    }
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(paths::config_dir()?.join("config.toml"))
+}
+
+pub fn load() -> Result<Config> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(&text).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+pub fn apply_env(config: &mut Config) -> Result<()> {
+    apply_env_from(config, |name| std::env::var(name).ok())
+}
+
+fn apply_env_from(config: &mut Config, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+    let var = |name: &str| var(name).filter(|v| !v.is_empty());
+    if let Some(level) = var("SSHPOD_LOG_LEVEL") {
+        config.log_level = level;
+    }
+    if let Some(ctx) = var("SSHPOD_CONTEXT") {
+        config.default_context = Some(ctx);
+    }
+    if let Some(ns) = var("SSHPOD_NAMESPACE") {
+        config.default_namespace = Some(ns);
+    }
+    if let Some(retries) = var("SSHPOD_KUBECTL_RETRIES") {
+        config.kubectl_retries = retries
+            .parse()
+            .with_context(|| format!("invalid SSHPOD_KUBECTL_RETRIES: {}", retries))?;
+    }
+    if let Some(secs) = var("SSHPOD_SSHD_TIMEOUT") {
+        config.sshd_startup_timeout_secs = secs
+            .parse()
+            .with_context(|| format!("invalid SSHPOD_SSHD_TIMEOUT: {}", secs))?;
+    }
+    Ok(())
+}
+
+fn parse(text: &str) -> Result<Config> {
+    Ok(toml::from_str(text)?)
+}
+
+// Set once in `cli::run` before argument parsing, since the values double as
+// clap defaults.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_partial_config() {
+        let config = parse("default_namespace = \"dev\"\nkubectl_retries = 2\n").unwrap();
+        assert_eq!(config.default_namespace.as_deref(), Some("dev"));
+        assert_eq!(config.kubectl_retries, 2);
+        assert_eq!(config.log_level, "info");
+        assert_eq!(config.sshd_startup_timeout_secs, 40);
+    }
+
+    #[test]
+    fn env_overrides_file_values() {
+        let mut config = parse("log_level = \"error\"\nkubectl_retries = 1\n").unwrap();
+        apply_env_from(&mut config, |name| match name {
+            "SSHPOD_KUBECTL_RETRIES" => Some("4".into()),
+            "SSHPOD_NAMESPACE" => Some("ci".into()),
+            "SSHPOD_LOG_LEVEL" => Some(String::new()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.kubectl_retries, 4);
+        assert_eq!(config.default_namespace.as_deref(), Some("ci"));
+        assert_eq!(config.log_level, "error");
+
+        let err = apply_env_from(&mut config, |name| {
+            (name == "SSHPOD_SSHD_TIMEOUT").then(|| "soon".into())
+        });
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(parse("log_lvl = \"debug\"\n").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::cli::CopyArgs;
+use crate::kubectl;
+use crate::proxy;
+use anyhow::{bail, Context, Result};
+use log::info;
+use std::path::{Path, PathBuf};
+
+const REMOTE_PREFIX: &str = "pod:";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Direction {
+    Upload { src: String, dest: String },
+    Download { src: String, dest: PathBuf },
+}
+
+pub async fn run(args: CopyArgs) -> Result<()> {
+    proxy::init_logger(&args.target.log_level);
+    let direction = direction(&args.src, &args.dest)?;
+    let (target, _) = proxy::resolve_from_args(&args.target).await?;
+
+    match direction {
+        Direction::Upload { src, dest } => {
+            let sources = expand_local(&src)?;
+            let into_dir = sources.len() > 1 || dest.ends_with('/');
+            for source in sources {
+                let remote = if into_dir {
+                    remote_child(&dest, &source)?
+                } else {
+                    dest.clone()
+                };
+                info!("[sshpod] copying {} to {}", source.display(), remote);
+                kubectl::copy_to_pod(
+                    &target.opts,
+                    &target.namespace,
+                    &target.pod,
+                    &target.container,
+                    &source,
+                    &remote,
+                )
+                .await?;
+            }
+        }
+        Direction::Download { src, dest } => {
+            let local = if dest.is_dir() {
+                dest.join(remote_basename(&src)?)
+            } else {
+                dest
+            };
+            info!("[sshpod] copying {} to {}", src, local.display());
+            kubectl::copy_from_pod(
+                &target.opts,
+                &target.namespace,
+                &target.pod,
+                &target.container,
+                &src,
+                &local,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+fn direction(src: &str, dest: &str) -> Result<Direction> {
+    match (
+        src.strip_prefix(REMOTE_PREFIX),
+        dest.strip_prefix(REMOTE_PREFIX),
+    ) {
+        (None, Some(remote)) if !remote.is_empty() => Ok(Direction::Upload {
+            src: src.to_string(),
+            dest: remote.to_string(),
+        }),
+        (Some(remote), None) if !remote.is_empty() => Ok(Direction::Download {
+            src: remote.to_string(),
+            dest: PathBuf::from(dest),
+        }),
+        (Some(_), Some(_)) => bail!("both paths are in the pod; one side must be local"),
+        (None, None) => bail!("neither path is in the pod; prefix the pod side with `pod:`"),
+        _ => bail!("the pod path after `pod:` must not be empty"),
+    }
+}
+
+fn expand_local(pattern: &str) -> Result<Vec<PathBuf>> {
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+    let mut paths = Vec::new();
+    for entry in glob::glob(pattern).with_context(|| format!("invalid glob `{}`", pattern))? {
+        paths.push(entry.with_context(|| format!("failed to read a match of `{}`", pattern))?);
+    }
+    if paths.is_empty() {
+        bail!("no local files match `{}`", pattern);
+    }
+    Ok(paths)
+}
+
+fn remote_child(dir: &str, source: &Path) -> Result<String> {
+    let name = source
+        .file_name()
+        .with_context(|| format!("cannot copy {} into a directory", source.display()))?;
+    Ok(format!(
+        "{}/{}",
+        dir.trim_end_matches('/'),
+        name.to_string_lossy()
+    ))
+}
+
+fn remote_basename(path: &str) -> Result<&str> {
+    match path.trim_end_matches('/').rsplit('/').next() {
+        Some(name) if !name.is_empty() => Ok(name),
+        _ => bail!("cannot derive a local file name from pod path `{}`", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direction_follows_pod_prefix() {
+        assert_eq!(
+            direction("./out/*.log", "pod:/tmp/logs/").unwrap(),
+            Direction::Upload {
+                src: "./out/*.log".into(),
+                dest: "/tmp/logs/".into()
+            }
+        );
+        assert_eq!(
+            direction("pod:/var/log/app.log", ".").unwrap(),
+            Direction::Download {
+                src: "/var/log/app.log".into(),
+                dest: PathBuf::from(".")
+            }
+        );
+        assert!(direction("a", "b").is_err());
+        assert!(direction("pod:/a", "pod:/b").is_err());
+        assert!(direction("a", "pod:").is_err());
+    }
+
+    #[test]
+    fn expands_local_globs() {
+        let dir = std::env::temp_dir().join(format!("sshpod-copy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a.log", "b.log", "c.txt"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+        let pattern = format!("{}/*.log", dir.display());
+        let matches = expand_local(&pattern).unwrap();
+        assert_eq!(matches, vec![dir.join("a.log"), dir.join("b.log")]);
+        assert!(expand_local(&format!("{}/*.none", dir.display())).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn names_files_inside_directories() {
+        assert_eq!(
+            remote_child("/tmp/logs/", Path::new("out/a.log")).unwrap(),
+            "/tmp/logs/a.log"
+        );
+        assert_eq!(remote_basename("/var/log/app/").unwrap(), "app");
+        assert!(remote_basename("/").is_err());
+    }
+}
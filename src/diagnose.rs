@@ -0,0 +1,80 @@
+use crate::check;
+use crate::cli::DiagnoseArgs;
+use crate::embedded;
+use crate::kubectl;
+use crate::proxy;
+use anyhow::{Context, Result};
+use std::fmt::Write;
+
+pub async fn run(args: DiagnoseArgs) -> Result<()> {
+    proxy::init_logger("error");
+    let mut out = String::new();
+
+    section(
+        &mut out,
+        "sshpod version",
+        Ok(env!("CARGO_PKG_VERSION").to_string()),
+    );
+
+    section(
+        &mut out,
+        "embedded bundles",
+        Ok(embedded::list_available_arches().join(", ")),
+    );
+
+    let opts = proxy::parse_host(&args.target)
+        .map(|host| proxy::kubectl_options(&host, &args.target))
+        .unwrap_or_default();
+    section(
+        &mut out,
+        "kubectl version",
+        kubectl::capture_combined(&opts, &["version"]).await,
+    );
+    section(
+        &mut out,
+        "kubectl cluster-info",
+        kubectl::capture_combined(&opts, &["cluster-info"]).await,
+    );
+
+    let report = check::sweep(&args.target).await;
+    section(
+        &mut out,
+        "sshpod check",
+        Ok(report.output.trim_end().to_string()),
+    );
+
+    section(&mut out, "sshd.log", remote_sshd_log(&args).await);
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, &out)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!("Wrote diagnostics to {}", path.display());
+        }
+        None => print!("{}", out),
+    }
+    Ok(())
+}
+
+fn section(out: &mut String, title: &str, body: Result<String>) {
+    let _ = writeln!(out, "===== {} =====", title);
+    match body {
+        Ok(text) => {
+            let _ = writeln!(out, "{}", text);
+        }
+        Err(err) => {
+            let _ = writeln!(out, "error: {:#}", err);
+        }
+    }
+    out.push('\n');
+}
+
+async fn remote_sshd_log(args: &DiagnoseArgs) -> Result<String> {
+    let (target, pod_info) = proxy::resolve_from_args(&args.target).await?;
+    let log_path = format!(
+        "{}/logs/sshd.log",
+        proxy::target_base(&args.target, &pod_info.uid, &target.container)?
+    );
+    let log = kubectl::exec_capture_optional_target(&target, &["cat", &log_path]).await?;
+    Ok(log.unwrap_or_else(|| format!("{} not found", log_path)))
+}
@@ -1,7 +1,53 @@
+/// Architectures with a bundle compiled into this binary; others need a bundle file.
+pub fn list_available_arches() -> &'static [&'static str] {
+    if cfg!(feature = "bundle-embed") {
+        &["linux/amd64", "linux/arm64"]
+    } else {
+        &[]
+    }
+}
+
+#[cfg(feature = "bundle-embed")]
 pub fn get_bundle(arch: &str) -> Option<&'static [u8]> {
     match arch {
         "linux/amd64" => Some(include_bytes!("../bundles/sshd_amd64.xz")),
         "linux/arm64" => Some(include_bytes!("../bundles/sshd_arm64.xz")),
+        // No embedded bundles yet; these are loaded from the filesystem instead.
+        "linux/riscv64" | "linux/s390x" | "linux/ppc64le" => None,
+        _ => None,
+    }
+}
+
+/// SHA-256 of the decompressed sshd in the embedded bundle, as written by `make bundles`.
+#[cfg(feature = "bundle-embed")]
+pub fn get_checksum(arch: &str) -> Option<&'static str> {
+    match arch {
+        "linux/amd64" => Some(include_str!("../bundles/sshd_amd64.sha256")),
+        "linux/arm64" => Some(include_str!("../bundles/sshd_arm64.sha256")),
         _ => None,
     }
 }
+
+// Built without `bundle-embed`: every bundle comes from the filesystem.
+#[cfg(not(feature = "bundle-embed"))]
+pub fn get_bundle(_arch: &str) -> Option<&'static [u8]> {
+    None
+}
+
+#[cfg(not(feature = "bundle-embed"))]
+pub fn get_checksum(_arch: &str) -> Option<&'static str> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_arches_have_bundles() {
+        for arch in list_available_arches() {
+            assert!(get_bundle(arch).is_some(), "{}", arch);
+        }
+        assert!(get_bundle("linux/riscv64").is_none());
+    }
+}
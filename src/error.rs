@@ -0,0 +1,51 @@
+use crate::hostspec::HostSpecError;
+use thiserror::Error;
+
+/// Failures worth telling apart programmatically. They travel inside
+/// [`anyhow::Error`], so match them with `err.downcast_ref::<SshpodError>()`.
+#[derive(Debug, Error)]
+pub enum SshpodError {
+    /// kubectl exited with an error.
+    // Only the subcommand: exec arguments can be whole shell scripts.
+    #[error("kubectl {} failed: {stderr}", args.first().map(String::as_str).unwrap_or_default())]
+    KubectlFailed {
+        /// Arguments after the global flags.
+        args: Vec<String>,
+        /// kubectl's stderr, trimmed.
+        stderr: String,
+    },
+    /// The pod does not exist.
+    #[error("pod `{name}` not found in namespace `{namespace}`")]
+    PodNotFound {
+        /// Namespace that was searched.
+        namespace: String,
+        /// Pod name.
+        name: String,
+    },
+    /// No sshd bundle exists for the container's `uname -m`.
+    #[error("unsupported remote architecture: {0}")]
+    ArchNotSupported(String),
+    /// Every way of uploading the bundle failed.
+    #[error("bundle install via {method} failed: {cause}")]
+    BundleInstallFailed {
+        /// The last method tried, e.g. `plain`.
+        method: String,
+        /// Why it failed.
+        cause: String,
+    },
+    /// sshd did not come up within the startup timeout.
+    #[error("sshd did not report its port in time")]
+    SshdStartTimeout,
+    /// The hostname is not a valid hostspec.
+    #[error(transparent)]
+    HostSpecParse(#[from] HostSpecError),
+}
+
+impl SshpodError {
+    pub(crate) fn kubectl_failed(args: &[&str], stderr: &str) -> Self {
+        SshpodError::KubectlFailed {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            stderr: stderr.trim().to_string(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::cli::ExecArgs;
+use crate::kubectl;
+use crate::proxy;
+use anyhow::Result;
+use tokio::io::AsyncRead;
+
+pub async fn run(args: ExecArgs) -> Result<()> {
+    proxy::init_logger(&args.target.log_level);
+    let (target, _) = proxy::resolve_from_args(&args.target).await?;
+    let command: Vec<&str> = args.command.iter().map(String::as_str).collect();
+
+    // Output is streamed as is, so binary output and long-running commands work.
+    let mut stdin = tokio::io::stdin();
+    let input: Option<&mut (dyn AsyncRead + Unpin + Send)> =
+        if args.stdin { Some(&mut stdin) } else { None };
+    let status = kubectl::exec_stream(
+        &target.opts,
+        target.namespace.as_str(),
+        target.pod.as_str(),
+        target.container.as_str(),
+        &command,
+        input,
+        &mut tokio::io::stdout(),
+    )
+    .await?;
+    // A read of local stdin may still be pending and would hold the runtime
+    // open until EOF, so leave with the command's status right away.
+    if args.stdin || !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
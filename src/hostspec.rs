@@ -1,32 +1,166 @@
 use thiserror::Error;
 
+/// A parsed `*.sshpod` hostname.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HostSpec {
+    /// kubectl context from `context--`.
     pub context: Option<String>,
+    /// Namespace from `namespace--`.
     pub namespace: Option<String>,
+    /// The workload or pod to connect to.
     pub target: Target,
+    /// Container from `container--` or `init-container--`.
     pub container: Option<String>,
+    /// Whether the container was given as `init-container--`.
+    pub init_container: bool,
+    /// Node from `node--`, used to pick a DaemonSet pod.
+    pub node: Option<String>,
 }
 
+impl HostSpec {
+    /// Formats the spec back into its canonical `*.sshpod` hostname.
+    pub fn to_hostname(&self) -> String {
+        let mut segments = Vec::new();
+        if let Some(container) = &self.container {
+            let prefix = if self.init_container {
+                "init-container"
+            } else {
+                "container"
+            };
+            segments.push(format!("{}--{}", prefix, container));
+        }
+        if let Some(node) = &self.node {
+            segments.push(format!("node--{}", node));
+        }
+        segments.push(match &self.target {
+            Target::Pod(name) => format!("pod--{}", name),
+            Target::Deployment(name) => format!("deployment--{}", name),
+            Target::Job(name) => format!("job--{}", name),
+            Target::CronJob(name) => format!("cronjob--{}", name),
+            Target::StatefulSet(name) => format!("statefulset--{}", name),
+            Target::DaemonSet(name) => format!("daemonset--{}", name),
+            Target::ReplicaSet(name) => format!("replicaset--{}", name),
+            Target::Service(name) => format!("service--{}", name),
+            Target::LabelSelector(selector) => format!("selector--{}", encode_selector(selector)),
+        });
+        if let Some(namespace) = &self.namespace {
+            segments.push(format!("namespace--{}", namespace));
+        }
+        if let Some(context) = &self.context {
+            segments.push(format!("context--{}", context));
+        }
+        segments.push("sshpod".to_string());
+        segments.join(".")
+    }
+
+    /// Checks names against Kubernetes naming rules; `parse` already calls this.
+    pub fn validate_kubernetes_names(&self) -> Result<(), HostSpecError> {
+        let target = match &self.target {
+            Target::Pod(name) => Some(("pod", name, 253)),
+            Target::Deployment(name) => Some(("deployment", name, 253)),
+            Target::Job(name) => Some(("job", name, 253)),
+            Target::CronJob(name) => Some(("cronjob", name, 253)),
+            Target::StatefulSet(name) => Some(("statefulset", name, 253)),
+            Target::DaemonSet(name) => Some(("daemonset", name, 253)),
+            Target::ReplicaSet(name) => Some(("replicaset", name, 253)),
+            Target::Service(name) => Some(("service", name, 63)),
+            Target::LabelSelector(_) => None,
+        };
+        if let Some((kind, name, max)) = target {
+            check_name(kind, name, max)?;
+        }
+        if let Some(namespace) = &self.namespace {
+            check_name("namespace", namespace, 63)?;
+        }
+        if let Some(container) = &self.container {
+            check_name("container", container, 63)?;
+        }
+        if let Some(node) = &self.node {
+            check_name("node", node, 253)?;
+        }
+        Ok(())
+    }
+}
+
+// Segments cannot contain dots, so DNS subdomains reduce to labels here:
+// lowercase alphanumerics and '-', starting and ending alphanumeric.
+fn check_name(kind: &str, name: &str, max: usize) -> Result<(), HostSpecError> {
+    let invalid = |reason: String| HostSpecError::InvalidResourceName {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        reason,
+    };
+    if name.len() > max {
+        return Err(invalid(format!("longer than {} characters", max)));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-'))
+    {
+        return Err(invalid(format!(
+            "'{}' is not allowed; use lowercase letters, digits and '-'",
+            c
+        )));
+    }
+    if name.starts_with('-') || name.ends_with('-') {
+        return Err(invalid("must start and end with a letter or digit".into()));
+    }
+    Ok(())
+}
+
+/// What a hostspec points at; each resolves to one Ready pod.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Target {
+    /// `pod--<name>`
     Pod(String),
+    /// `deployment--<name>`
     Deployment(String),
+    /// `job--<name>`
     Job(String),
+    /// `statefulset--<name>`
+    StatefulSet(String),
+    /// `daemonset--<name>`
+    DaemonSet(String),
+    /// `selector--<selector>`, already decoded to `k=v,...` form.
+    LabelSelector(String),
+    /// `cronjob--<name>`
+    CronJob(String),
+    /// `service--<name>`
+    Service(String),
+    /// `replicaset--<name>`
+    ReplicaSet(String),
 }
 
+/// Why a hostname is not a valid hostspec.
 #[derive(Debug, Error)]
 pub enum HostSpecError {
+    /// The hostname does not end with `.sshpod`.
     #[error("hostname must end with .sshpod")]
     MissingSuffix,
+    /// A segment is not of the form `<kind>--<value>`.
     #[error("hostname segment '{segment}' is missing \"--\"")]
-    MissingSeparator { segment: String },
+    MissingSeparator {
+        /// The offending segment.
+        segment: String,
+    },
     #[error(
-        "hostname must include one of pod--/deployment--/job-- (container-- optional, namespace-- optional, context-- optional), ending with .sshpod"
+        "hostname must include one of pod--/deployment--/job--/cronjob--/statefulset--/daemonset--/replicaset--/service--/selector-- (container-- or init-container-- optional, node-- optional with daemonset--, namespace-- optional, context-- optional), ending with .sshpod"
     )]
+    /// No target segment, or segments that cannot be combined.
     InvalidFormat,
+    /// A name breaks Kubernetes naming rules.
+    #[error("invalid {kind} name '{name}': {reason}")]
+    InvalidResourceName {
+        /// Kind of the name, e.g. `deployment` or `namespace`.
+        kind: String,
+        /// The name as written.
+        name: String,
+        /// Which rule it breaks.
+        reason: String,
+    },
 }
 
+/// Parses a `*.sshpod` hostname such as `container--app.deployment--web.namespace--prod.sshpod`.
 pub fn parse(host: &str) -> Result<HostSpec, HostSpecError> {
     let trimmed = host.trim_end_matches('.');
     let without_suffix = trimmed
@@ -34,52 +168,133 @@ pub fn parse(host: &str) -> Result<HostSpec, HostSpecError> {
         .ok_or(HostSpecError::MissingSuffix)?;
 
     let mut container = None;
+    let mut init_container = false;
     let mut namespace = None;
     let mut context = None;
+    let mut node = None;
     let mut target = None;
 
     for token in without_suffix.split('.').filter(|s| !s.is_empty()) {
-        if !token.contains("--") {
-            return Err(HostSpecError::MissingSeparator {
-                segment: token.to_string(),
-            });
-        }
-        if let Some(rest) = token.strip_prefix("container--") {
-            if rest.is_empty() || container.is_some() {
-                return Err(HostSpecError::InvalidFormat);
+        match parse_segment(token)? {
+            Segment::Container(name, init) if container.is_none() => {
+                container = Some(name);
+                init_container = init;
             }
-            container = Some(rest.to_string());
-            continue;
+            Segment::Namespace(name) if namespace.is_none() => namespace = Some(name),
+            Segment::Context(name) if context.is_none() => context = Some(name),
+            Segment::Node(name) if node.is_none() => node = Some(name),
+            Segment::Target(t) if target.is_none() => target = Some(t),
+            _ => return Err(HostSpecError::InvalidFormat),
         }
-        if let Some(rest) = token.strip_prefix("namespace--") {
-            if rest.is_empty() || namespace.is_some() {
-                return Err(HostSpecError::InvalidFormat);
-            }
-            namespace = Some(rest.to_string());
-            continue;
-        }
-        if let Some(rest) = token.strip_prefix("context--") {
-            if rest.is_empty() || context.is_some() {
-                return Err(HostSpecError::InvalidFormat);
-            }
-            context = Some(rest.to_string());
-            continue;
-        }
-        if target.is_none() {
-            target = Some(parse_target(token)?);
-            continue;
-        }
-        return Err(HostSpecError::InvalidFormat);
     }
 
     let target = target.ok_or(HostSpecError::InvalidFormat)?;
+    if node.is_some() && !matches!(target, Target::DaemonSet(_)) {
+        return Err(HostSpecError::InvalidFormat);
+    }
 
-    Ok(HostSpec {
+    let spec = HostSpec {
         target,
         namespace,
         context,
         container,
-    })
+        init_container,
+        node,
+    };
+    spec.validate_kubernetes_names()?;
+    Ok(spec)
+}
+
+/// Whatever could be read from a possibly incomplete hostspec.
+#[derive(Debug, Default)]
+pub struct HostSpecPartial {
+    pub target: Option<Target>,
+    pub namespace: Option<String>,
+    pub context: Option<String>,
+    pub container: Option<String>,
+    pub errors: Vec<HostSpecError>,
+}
+
+// Never fails: bad or repeated segments are recorded and skipped, the first
+// occurrence of each field wins, and the .sshpod suffix is optional.
+pub fn parse_lenient(host: &str) -> HostSpecPartial {
+    let mut partial = HostSpecPartial::default();
+    let trimmed = host.trim_end_matches('.');
+    let body = match trimmed.strip_suffix(".sshpod") {
+        Some(body) => body,
+        None => {
+            partial.errors.push(HostSpecError::MissingSuffix);
+            trimmed
+        }
+    };
+    for token in body.split('.').filter(|s| !s.is_empty()) {
+        let repeated = match parse_segment(token) {
+            Ok(Segment::Container(name, _)) => fill(&mut partial.container, name),
+            Ok(Segment::Namespace(name)) => fill(&mut partial.namespace, name),
+            Ok(Segment::Context(name)) => fill(&mut partial.context, name),
+            Ok(Segment::Node(_)) => false,
+            Ok(Segment::Target(target)) => fill(&mut partial.target, target),
+            Err(err) => {
+                partial.errors.push(err);
+                false
+            }
+        };
+        if repeated {
+            partial.errors.push(HostSpecError::InvalidFormat);
+        }
+    }
+    if partial.target.is_none() {
+        partial.errors.push(HostSpecError::InvalidFormat);
+    }
+    partial
+}
+
+// Returns true when the slot was already taken.
+fn fill<T>(slot: &mut Option<T>, value: T) -> bool {
+    if slot.is_some() {
+        return true;
+    }
+    *slot = Some(value);
+    false
+}
+
+enum Segment {
+    Container(String, bool),
+    Namespace(String),
+    Context(String),
+    Node(String),
+    Target(Target),
+}
+
+fn parse_segment(token: &str) -> Result<Segment, HostSpecError> {
+    if !token.contains("--") {
+        return Err(HostSpecError::MissingSeparator {
+            segment: token.to_string(),
+        });
+    }
+    let named = |rest: &str| {
+        if rest.is_empty() {
+            Err(HostSpecError::InvalidFormat)
+        } else {
+            Ok(rest.to_string())
+        }
+    };
+    if let Some(rest) = token.strip_prefix("container--") {
+        return Ok(Segment::Container(named(rest)?, false));
+    }
+    if let Some(rest) = token.strip_prefix("init-container--") {
+        return Ok(Segment::Container(named(rest)?, true));
+    }
+    if let Some(rest) = token.strip_prefix("namespace--") {
+        return Ok(Segment::Namespace(named(rest)?));
+    }
+    if let Some(rest) = token.strip_prefix("context--") {
+        return Ok(Segment::Context(named(rest)?));
+    }
+    if let Some(rest) = token.strip_prefix("node--") {
+        return Ok(Segment::Node(named(rest)?));
+    }
+    parse_target(token).map(Segment::Target)
 }
 
 fn parse_target(token: &str) -> Result<Target, HostSpecError> {
@@ -104,9 +319,203 @@ fn parse_target(token: &str) -> Result<Target, HostSpecError> {
         }
         return Ok(Target::Job(rest.to_string()));
     }
+    if let Some(rest) = token.strip_prefix("cronjob--") {
+        if rest.is_empty() {
+            return Err(HostSpecError::InvalidFormat);
+        }
+        return Ok(Target::CronJob(rest.to_string()));
+    }
+    if let Some(rest) = token.strip_prefix("statefulset--") {
+        if rest.is_empty() {
+            return Err(HostSpecError::InvalidFormat);
+        }
+        return Ok(Target::StatefulSet(rest.to_string()));
+    }
+    if let Some(rest) = token.strip_prefix("daemonset--") {
+        if rest.is_empty() {
+            return Err(HostSpecError::InvalidFormat);
+        }
+        return Ok(Target::DaemonSet(rest.to_string()));
+    }
+    if let Some(rest) = token.strip_prefix("replicaset--") {
+        if rest.is_empty() {
+            return Err(HostSpecError::InvalidFormat);
+        }
+        return Ok(Target::ReplicaSet(rest.to_string()));
+    }
+    if let Some(rest) = token.strip_prefix("service--") {
+        if rest.is_empty() {
+            return Err(HostSpecError::InvalidFormat);
+        }
+        return Ok(Target::Service(rest.to_string()));
+    }
+    if let Some(rest) = token
+        .strip_prefix("selector--")
+        .or_else(|| token.strip_prefix("label--"))
+    {
+        if rest.is_empty() {
+            return Err(HostSpecError::InvalidFormat);
+        }
+        return Ok(Target::LabelSelector(decode_selector(rest)?));
+    }
     Err(HostSpecError::InvalidFormat)
 }
 
+const SEGMENT_KINDS: &[&str] = &[
+    "init-container",
+    "statefulset",
+    "replicaset",
+    "deployment",
+    "daemonset",
+    "namespace",
+    "container",
+    "selector",
+    "cronjob",
+    "context",
+    "service",
+    "label",
+    "node",
+    "pod",
+    "job",
+];
+
+const KIND_ALIASES: &[(&str, &str)] = &[
+    ("po", "pod"),
+    ("deploy", "deployment"),
+    ("sts", "statefulset"),
+    ("ds", "daemonset"),
+    ("rs", "replicaset"),
+    ("svc", "service"),
+    ("cj", "cronjob"),
+    ("ns", "namespace"),
+    ("ctx", "context"),
+];
+
+// Plausible fixes for a hostspec that failed to parse, each one parseable.
+pub fn suggest_corrections(host: &str) -> Vec<String> {
+    let trimmed = host.trim_end_matches('.');
+    let body = trimmed.strip_suffix(".sshpod").unwrap_or(trimmed);
+    let segments: Vec<String> = body
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(fix_segment)
+        .collect();
+
+    let has_target = segments
+        .iter()
+        .any(|s| matches!(parse_segment(s), Ok(Segment::Target(_))));
+    let mut candidates = Vec::new();
+    if has_target {
+        candidates.push(segments);
+    } else {
+        // A bare segment is most likely the resource name missing its kind.
+        let bare = segments.iter().position(|s| !s.contains("--"));
+        for kind in ["pod", "deployment", "job"] {
+            let mut candidate = segments.clone();
+            match bare {
+                Some(i) => candidate[i] = format!("{}--{}", kind, segments[i]),
+                None => candidate.insert(0, format!("{}--<name>", kind)),
+            }
+            candidates.push(candidate);
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    for candidate in candidates {
+        let hostname = format!("{}.sshpod", candidate.join("."));
+        if hostname != host
+            && parse(&hostname.replace("<name>", "name")).is_ok()
+            && !suggestions.contains(&hostname)
+        {
+            suggestions.push(hostname);
+        }
+    }
+    suggestions
+}
+
+fn fix_segment(segment: &str) -> String {
+    let (kind, rest) = match segment.split_once("--") {
+        Some((kind, rest)) => match closest_kind(kind) {
+            Some(kind) => (kind, rest),
+            None => return segment.to_string(),
+        },
+        // A single dash after a known kind, e.g. `pod-web`.
+        None => match SEGMENT_KINDS
+            .iter()
+            .find_map(|kind| Some((*kind, segment.strip_prefix(kind)?.strip_prefix('-')?)))
+        {
+            Some(found) => found,
+            None => return segment.to_string(),
+        },
+    };
+    match kind {
+        "context" | "selector" | "label" => format!("{}--{}", kind, rest),
+        _ => format!("{}--{}", kind, rest.to_ascii_lowercase()),
+    }
+}
+
+fn closest_kind(kind: &str) -> Option<&'static str> {
+    let lower = kind.to_ascii_lowercase();
+    if let Some(known) = SEGMENT_KINDS.iter().find(|k| **k == lower) {
+        return Some(known);
+    }
+    if let Some((_, known)) = KIND_ALIASES.iter().find(|(alias, _)| *alias == lower) {
+        return Some(known);
+    }
+    SEGMENT_KINDS
+        .iter()
+        .map(|k| (edit_distance(k, &lower), *k))
+        .filter(|(distance, k)| *distance <= if k.len() <= 4 { 1 } else { 2 })
+        .min()
+        .map(|(_, k)| k)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != *cb);
+            row.push(substitute.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+pub fn encode_selector(selector: &str) -> String {
+    let mut out = String::with_capacity(selector.len());
+    for b in selector.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn decode_selector(value: &str) -> Result<String, HostSpecError> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .ok_or(HostSpecError::InvalidFormat)?;
+            let byte = u8::from_str_radix(hex, 16).map_err(|_| HostSpecError::InvalidFormat)?;
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| HostSpecError::InvalidFormat)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,6 +559,209 @@ mod tests {
         assert_eq!(spec.context.as_deref(), Some("ctx"));
     }
 
+    #[test]
+    fn node_segment_requires_daemonset() {
+        let spec = parse("node--worker-1.daemonset--fluentd.namespace--logging.sshpod")
+            .expect("node with daemonset should parse");
+        assert_eq!(spec.target, Target::DaemonSet("fluentd".into()));
+        assert_eq!(spec.node.as_deref(), Some("worker-1"));
+
+        let spec = parse("daemonset--fluentd.sshpod").expect("daemonset without node");
+        assert_eq!(spec.node, None);
+
+        assert!(matches!(
+            parse("node--worker-1.pod--a.sshpod"),
+            Err(HostSpecError::InvalidFormat)
+        ));
+        assert!(matches!(
+            parse("node--worker-1.service--api.sshpod"),
+            Err(HostSpecError::InvalidFormat)
+        ));
+        assert!(matches!(
+            parse("node--.daemonset--fluentd.sshpod"),
+            Err(HostSpecError::InvalidFormat)
+        ));
+        assert!(matches!(
+            parse("node--a.node--b.daemonset--d.sshpod"),
+            Err(HostSpecError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn init_container_segment() {
+        let spec = parse("init-container--setup.pod--a.namespace--n.sshpod")
+            .expect("init container should parse");
+        assert_eq!(spec.container.as_deref(), Some("setup"));
+        assert!(spec.init_container);
+
+        let spec = parse("container--main.pod--a.sshpod").expect("container should parse");
+        assert!(!spec.init_container);
+
+        assert!(parse("init-container--setup.container--main.pod--a.sshpod").is_err());
+        assert!(parse("container--main.init-container--setup.pod--a.sshpod").is_err());
+        assert!(parse("init-container--.pod--a.sshpod").is_err());
+    }
+
+    #[test]
+    fn suggests_corrections_for_common_typos() {
+        // Single dash instead of two.
+        assert_eq!(
+            suggest_corrections("deployment--ws.context-prod.sshpod"),
+            ["deployment--ws.context--prod.sshpod"]
+        );
+        // Missing .sshpod suffix.
+        assert_eq!(
+            suggest_corrections("pod--web.namespace--n"),
+            ["pod--web.namespace--n.sshpod"]
+        );
+        // Resource name without a kind.
+        assert_eq!(
+            suggest_corrections("web.namespace--n.sshpod"),
+            [
+                "pod--web.namespace--n.sshpod",
+                "deployment--web.namespace--n.sshpod",
+                "job--web.namespace--n.sshpod"
+            ]
+        );
+        // No target at all.
+        assert_eq!(
+            suggest_corrections("namespace--n.sshpod")[0],
+            "pod--<name>.namespace--n.sshpod"
+        );
+        // kubectl short names and misspelled kinds.
+        assert_eq!(
+            suggest_corrections("deploy--web.ns--prod.sshpod"),
+            ["deployment--web.namespace--prod.sshpod"]
+        );
+        assert_eq!(
+            suggest_corrections("deploymnet--web.sshpod"),
+            ["deployment--web.sshpod"]
+        );
+        // Uppercase resource names.
+        assert_eq!(
+            suggest_corrections("pod--Web-0.context--Prod.sshpod"),
+            ["pod--web-0.context--Prod.sshpod"]
+        );
+        assert!(suggest_corrections("totally wrong").len() <= 3);
+    }
+
+    #[test]
+    fn lenient_parse_keeps_what_it_can() {
+        let partial = parse_lenient("pod--myapp.");
+        assert_eq!(partial.target, Some(Target::Pod("myapp".into())));
+        assert!(matches!(partial.errors[..], [HostSpecError::MissingSuffix]));
+
+        let partial = parse_lenient("pod--a.bogus.namespace--n.namespace--m.context--c.sshpod");
+        assert_eq!(partial.target, Some(Target::Pod("a".into())));
+        assert_eq!(partial.namespace.as_deref(), Some("n"));
+        assert_eq!(partial.context.as_deref(), Some("c"));
+        assert_eq!(partial.errors.len(), 2);
+
+        let partial = parse_lenient("namespace--n.container--");
+        assert_eq!(partial.target, None);
+        assert_eq!(partial.namespace.as_deref(), Some("n"));
+        assert_eq!(partial.container, None);
+        assert!(!partial.errors.is_empty());
+    }
+
+    #[test]
+    fn rejects_illegal_resource_names() {
+        assert!(parse("pod--web-0.namespace--team-a.sshpod").is_ok());
+        let err = parse("pod--My_Pod.sshpod").unwrap_err();
+        assert!(matches!(
+            err,
+            HostSpecError::InvalidResourceName { ref kind, ref name, .. }
+                if kind == "pod" && name == "My_Pod"
+        ));
+        assert!(parse("deployment--web.namespace--MY_NS.sshpod").is_err());
+        assert!(parse("pod--web.namespace--team-.sshpod").is_err());
+        assert!(parse(&format!("pod--a.namespace--{}.sshpod", "n".repeat(64))).is_err());
+        assert!(parse(&format!("pod--{}.sshpod", "p".repeat(254))).is_err());
+        assert!(parse("container--Main.pod--a.sshpod").is_err());
+        // Selectors and contexts are not resource names.
+        assert!(parse("selector--App%3DWeb.context--Prod_Cluster.sshpod").is_ok());
+    }
+
+    #[test]
+    fn service_target() {
+        let spec = parse("service--my-api.namespace--prod.sshpod").expect("service should parse");
+        assert_eq!(spec.target, Target::Service("my-api".into()));
+        assert_eq!(spec.namespace.as_deref(), Some("prod"));
+        assert_eq!(spec.to_hostname(), "service--my-api.namespace--prod.sshpod");
+        assert!(parse("service--.sshpod").is_err());
+    }
+
+    #[test]
+    fn selector_value_is_decoded() {
+        let spec = parse("selector--app%3Dweb%2Ctier%3Dfront.namespace--n.sshpod")
+            .expect("selector should parse");
+        assert_eq!(
+            spec.target,
+            Target::LabelSelector("app=web,tier=front".into())
+        );
+        let spec = parse("label--app%2Ekubernetes%2Eio%2Fname%3Dapi.sshpod")
+            .expect("label alias should parse");
+        assert_eq!(
+            spec.target,
+            Target::LabelSelector("app.kubernetes.io/name=api".into())
+        );
+        assert!(parse("selector--app%3.sshpod").is_err());
+        assert!(parse("selector--app%ZZ.sshpod").is_err());
+    }
+
+    #[test]
+    fn selector_encoding_round_trips() {
+        let selector = "app.kubernetes.io/name in (api,web),!canary";
+        let encoded = encode_selector(selector);
+        assert!(!encoded.contains('.'));
+        assert!(!encoded.contains('='));
+        assert!(!encoded.contains(','));
+        assert_eq!(encode_selector("app=web,tier=db"), "app%3Dweb%2Ctier%3Ddb");
+        let spec = parse(&format!("selector--{}.sshpod", encoded)).expect("encoded selector");
+        assert_eq!(spec.target, Target::LabelSelector(selector.into()));
+    }
+
+    #[test]
+    fn to_hostname_round_trips() {
+        let targets = [
+            Target::Pod("api-7d9f-x2x".into()),
+            Target::Pod("a--b".into()),
+            Target::Deployment("web".into()),
+            Target::Job("batch-1".into()),
+            Target::CronJob("nightly".into()),
+            Target::StatefulSet("db".into()),
+            Target::DaemonSet("fluentd".into()),
+            Target::ReplicaSet("web-5d8f7c".into()),
+            Target::Service("api".into()),
+            Target::LabelSelector("app.kubernetes.io/name=api,tier!=cache".into()),
+        ];
+        let names = [None, Some("x"), Some("pod--y"), Some("ctx-with--dashes")];
+        for target in &targets {
+            for container in names {
+                for namespace in names {
+                    for context in names {
+                        let node = match target {
+                            Target::DaemonSet(_) => context.map(|_| "worker-1".to_string()),
+                            _ => None,
+                        };
+                        let spec = HostSpec {
+                            context: context.map(String::from),
+                            namespace: namespace.map(String::from),
+                            target: target.clone(),
+                            container: container.map(String::from),
+                            init_container: container.is_some() && matches!(target, Target::Job(_)),
+                            node,
+                        };
+                        let hostname = spec.to_hostname();
+                        let parsed = parse(&hostname)
+                            .unwrap_or_else(|e| panic!("{} failed to parse: {}", hostname, e));
+                        assert_eq!(parsed, spec, "round trip of {}", hostname);
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn round_trip_common_patterns() {
         let cases = [
@@ -163,6 +775,18 @@ mod tests {
                 ("d", Some("c"), Some("n"), None),
             ),
             ("job--j.context--c.sshpod", ("j", Some("c"), None, None)),
+            (
+                "cronjob--cj.namespace--etl.sshpod",
+                ("cj", None, Some("etl"), None),
+            ),
+            (
+                "statefulset--db.namespace--n.sshpod",
+                ("db", None, Some("n"), None),
+            ),
+            (
+                "replicaset--web-5d8f7c.namespace--n.sshpod",
+                ("web-5d8f7c", None, Some("n"), None),
+            ),
             (
                 "container--x.pod--a.namespace--n.context--c.sshpod",
                 ("a", Some("c"), Some("n"), Some("x")),
@@ -175,7 +799,15 @@ mod tests {
         for (input, (name, ctx, ns, container)) in cases {
             let spec = parse(input).expect("should parse");
             match &spec.target {
-                Target::Pod(p) | Target::Deployment(p) | Target::Job(p) => assert_eq!(p, name),
+                Target::Pod(p)
+                | Target::Deployment(p)
+                | Target::Job(p)
+                | Target::CronJob(p)
+                | Target::StatefulSet(p)
+                | Target::DaemonSet(p)
+                | Target::Service(p)
+                | Target::ReplicaSet(p)
+                | Target::LabelSelector(p) => assert_eq!(p, name),
             }
             assert_eq!(spec.context.as_deref(), ctx);
             assert_eq!(spec.namespace.as_deref(), ns);
@@ -1,21 +1,115 @@
+use crate::cli::ConfigureArgs;
 use crate::paths;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const START_MARKER: &str = "# >>> sshpod start";
 const END_MARKER: &str = "# <<< sshpod end";
+pub(crate) const SERVER_ALIVE_INTERVAL: u32 = 30;
+const SERVER_ALIVE_COUNT_MAX: u32 = 3;
+const CONNECT_TIMEOUT: u32 = 30;
+const CONTROL_PERSIST: &str = "10m";
 
-pub async fn run() -> Result<()> {
-    let ssh_dir = paths::home_dir()?.join(".ssh");
-    fs::create_dir_all(&ssh_dir)
-        .with_context(|| format!("failed to create {}", ssh_dir.display()))?;
+pub async fn run(args: ConfigureArgs) -> Result<()> {
+    let home = paths::home_dir()?;
+    let cache_dir = paths::cache_dir()?;
+    let sshpod_path = match &args.sshpod_path {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => detect_sshpod_binary_path(),
+    };
+    configure_in(
+        &paths::ssh_dir()?,
+        &ssh_path(&cache_dir, &home),
+        &quote_ssh_arg(sshpod_path),
+        &SshConfigOptions {
+            forward_agent: !args.no_forward_agent,
+            server_alive_interval: args.server_alive_interval,
+            identity_file: args.identity_file.clone(),
+            ..SshConfigOptions::default()
+        },
+        args.dry_run,
+    )?;
+    if !args.dry_run {
+        prepare_private_dir(&cache_dir.join("ctl"))?;
+        println!(
+            "Optional settings file: {}",
+            paths::config_dir()?.join("config.toml").display()
+        );
+    }
+    Ok(())
+}
+
+fn configure_in(
+    ssh_dir: &Path,
+    cache_dir: &str,
+    proxy_command: &str,
+    opts: &SshConfigOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let config_path = ssh_dir.join("config");
+    let current = read_config(&config_path)?;
+
+    let block = render_block_with_options(proxy_command, cache_dir, opts);
+    let updated = merge_config(&current, &block);
+    for lines in detect_conflicting_config(&current) {
+        eprintln!(
+            "warning: Existing non-sshpod Host *.sshpod block found at lines {}; it may override the installed configuration.",
+            lines
+        );
+    }
+
+    if dry_run {
+        print!("{}", updated);
+        return Ok(());
+    }
+    if current == updated {
+        println!("No changes needed for {}", config_path.display());
+        return Ok(());
+    }
+    prepare_private_dir(ssh_dir)?;
+    write_config(ssh_dir, &updated)
+}
+
+pub async fn uninstall() -> Result<()> {
+    uninstall_in(&paths::ssh_dir()?)
+}
+
+fn uninstall_in(ssh_dir: &Path) -> Result<()> {
+    let config_path = ssh_dir.join("config");
+    let current = read_config(&config_path)?;
+    if !current.lines().any(|line| line.trim() == START_MARKER) {
+        println!("No sshpod block found in {}", config_path.display());
+        return Ok(());
+    }
+
+    let updated = merge_config(&current, "");
+    write_config(ssh_dir, &updated)?;
+    println!("Removed the sshpod block from {}", config_path.display());
+    Ok(())
+}
+
+fn prepare_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let _ = fs::set_permissions(&ssh_dir, fs::Permissions::from_mode(0o700));
+        let _ = fs::set_permissions(dir, fs::Permissions::from_mode(0o700));
     }
+    Ok(())
+}
 
+fn read_config(config_path: &Path) -> Result<String> {
+    if config_path.exists() {
+        fs::read_to_string(config_path)
+            .with_context(|| format!("failed to read {}", config_path.display()))
+    } else {
+        Ok(String::new())
+    }
+}
+
+fn write_config(ssh_dir: &Path, updated: &str) -> Result<()> {
     let config_path = ssh_dir.join("config");
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -25,21 +119,6 @@ pub async fn run() -> Result<()> {
         .exists()
         .then(|| ssh_dir.join(format!("config.bak.{}", timestamp)));
 
-    let current = if config_path.exists() {
-        fs::read_to_string(&config_path)
-            .with_context(|| format!("failed to read {}", config_path.display()))?
-    } else {
-        String::new()
-    };
-
-    let proxy_command = proxy_command_path()?;
-    let updated = merge_config(&current, &render_block(&proxy_command));
-
-    if current == updated {
-        println!("No changes needed for {}", config_path.display());
-        return Ok(());
-    }
-
     if let Some(backup) = backup_path.as_ref() {
         fs::copy(&config_path, backup)
             .with_context(|| format!("failed to create backup {}", backup.as_path().display()))?;
@@ -67,7 +146,83 @@ pub async fn run() -> Result<()> {
     Ok(())
 }
 
-fn render_block(proxy_command: &str) -> String {
+// Paths under the home directory are written as `~/...` so the block stays
+// readable and survives a renamed home.
+fn ssh_path(path: &Path, home: &Path) -> String {
+    let rendered = match path.strip_prefix(home) {
+        Ok(rest) => PathBuf::from("~").join(rest),
+        Err(_) => path.to_path_buf(),
+    };
+    rendered.to_string_lossy().replace('\\', "/")
+}
+
+fn quote_ssh_arg(value: String) -> String {
+    if value.contains(' ') {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// Settings of the generated `Host *.sshpod` block.
+#[derive(Debug, Clone)]
+pub struct SshConfigOptions {
+    /// `ForwardAgent yes`
+    pub forward_agent: bool,
+    /// `ServerAliveInterval` in seconds.
+    pub server_alive_interval: u32,
+    /// `ConnectTimeout` in seconds.
+    pub connect_timeout: u32,
+    /// Key tried before sshpod's own.
+    pub identity_file: Option<PathBuf>,
+    /// Further `Key Value` lines appended to the block.
+    pub extra_options: Vec<(String, String)>,
+}
+
+impl Default for SshConfigOptions {
+    fn default() -> Self {
+        Self {
+            forward_agent: true,
+            server_alive_interval: SERVER_ALIVE_INTERVAL,
+            connect_timeout: CONNECT_TIMEOUT,
+            identity_file: None,
+            extra_options: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+fn render_block(proxy_command: &str, cache_dir: &str) -> String {
+    render_block_with_options(proxy_command, cache_dir, &SshConfigOptions::default())
+}
+
+pub fn render_block_with_options(
+    proxy_command: &str,
+    cache_dir: &str,
+    opts: &SshConfigOptions,
+) -> String {
+    let mut identities = String::new();
+    if let Some(path) = &opts.identity_file {
+        identities.push_str(&format!(
+            "  IdentityFile {}\n",
+            quote_ssh_arg(path.to_string_lossy().replace('\\', "/"))
+        ));
+    }
+    let extra: String = opts
+        .extra_options
+        .iter()
+        .map(|(key, value)| format!("  {} {}\n", key, value))
+        .collect();
+    // Win32-OpenSSH cannot create the multiplexing socket and fails the connection.
+    let control = if cfg!(windows) {
+        String::new()
+    } else {
+        format!(
+            "  ControlMaster auto\n  ControlPath {}\n  ControlPersist {}\n",
+            quote_ssh_arg(format!("{}/ctl/%C", cache_dir)),
+            CONTROL_PERSIST
+        )
+    };
     format!(
         r#"{start}
 Host *.sshpod
@@ -76,44 +231,88 @@ Host *.sshpod
   UserKnownHostsFile /dev/null
   GlobalKnownHostsFile /dev/null
   CheckHostIP no
-  IdentityFile ~/.cache/sshpod/id_ed25519
+{identities}  IdentityFile {ed25519_key}
+  IdentityFile {rsa_key}
   IdentitiesOnly yes
   BatchMode yes
-  ForwardAgent yes
-{end}
+  ForwardAgent {forward_agent}
+  ServerAliveInterval {server_alive_interval}
+  ServerAliveCountMax {server_alive_count_max}
+  ConnectTimeout {connect_timeout}
+{control}{extra}{end}
 "#,
         start = START_MARKER,
         proxy_command = proxy_command,
+        identities = identities,
+        ed25519_key = quote_ssh_arg(format!("{}/id_ed25519", cache_dir)),
+        rsa_key = quote_ssh_arg(format!("{}/id_rsa", cache_dir)),
+        forward_agent = if opts.forward_agent { "yes" } else { "no" },
+        server_alive_interval = opts.server_alive_interval,
+        server_alive_count_max = SERVER_ALIVE_COUNT_MAX,
+        connect_timeout = opts.connect_timeout,
+        control = control,
+        extra = extra,
         end = END_MARKER
     )
 }
 
-fn proxy_command_path() -> Result<String> {
-    let exe = std::env::current_exe()
-        .context("failed to resolve current executable path; run sshpod from a real binary path")?;
-
-    let mut path = exe
-        .to_str()
-        .ok_or_else(|| anyhow!("executable path contains invalid UTF-8: {}", exe.display()))?
-        .to_string();
+/// Finds the sshpod binary for ProxyCommand: the running executable, then
+/// `sshpod` on PATH, then `~/.local/bin/sshpod`.
+pub fn detect_sshpod_binary_path() -> String {
+    let running = std::env::current_exe().ok();
+    let on_path = || which::which("sshpod").ok();
+    running
+        .and_then(|exe| exe.to_str().map(normalize_exe_path))
+        .or_else(|| on_path().and_then(|exe| exe.to_str().map(normalize_exe_path)))
+        .unwrap_or_else(|| "~/.local/bin/sshpod".to_string())
+}
 
+fn normalize_exe_path(path: &str) -> String {
     // `std::env::current_exe` on Windows can return the extended-length path (\\?\C:\...).
     const UNC_PREFIX: &str = "\\\\?\\";
-    if let Some(stripped) = path.strip_prefix(UNC_PREFIX) {
-        path = stripped.to_string();
-    }
+    let path = path.strip_prefix(UNC_PREFIX).unwrap_or(path);
 
-    #[cfg(windows)]
-    {
-        // OpenSSH on Windows accepts forward slashes and they avoid backslash-escape pitfalls.
-        path = path.replace('\\', "/");
+    // OpenSSH on Windows accepts forward slashes and they avoid backslash-escape pitfalls.
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
     }
+}
 
-    if path.contains(' ') {
-        path = format!("\"{}\"", path);
+/// Line ranges (1-based, e.g. `"4-9"`) of `Host` blocks matching `*.sshpod`
+/// outside the sshpod markers, which ssh would apply before or after ours.
+pub fn detect_conflicting_config(content: &str) -> Vec<String> {
+    let mut ranges = Vec::new();
+    let mut open: Option<(usize, usize)> = None;
+    let mut managed = false;
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let trimmed = line.trim();
+        let mut words = trimmed.split(|c: char| c.is_whitespace() || c == '=');
+        let keyword = words.next().unwrap_or("").to_ascii_lowercase();
+        let starts_block = keyword == "host" || keyword == "match";
+        if starts_block || trimmed == START_MARKER {
+            if let Some((start, end)) = open.take() {
+                ranges.push(format!("{}-{}", start, end));
+            }
+        }
+        if trimmed == START_MARKER {
+            managed = true;
+        } else if trimmed == END_MARKER {
+            managed = false;
+        } else if managed {
+            continue;
+        } else if keyword == "host" && words.any(|pattern| pattern == "*.sshpod") {
+            open = Some((number, number));
+        } else if let Some((_, end)) = open.as_mut().filter(|_| !trimmed.is_empty()) {
+            *end = number;
+        }
     }
-
-    Ok(path)
+    if let Some((start, end)) = open {
+        ranges.push(format!("{}-{}", start, end));
+    }
+    ranges
 }
 
 fn merge_config(current: &str, block: &str) -> String {
@@ -140,9 +339,135 @@ fn merge_config(current: &str, block: &str) -> String {
     let mut result = String::new();
     if !kept.is_empty() {
         result.push_str(&kept.join("\n"));
-        result.push_str("\n\n");
+        result.push('\n');
+    }
+    let block = block.trim_end();
+    if !block.is_empty() {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(block);
+        result.push('\n');
     }
-    result.push_str(block.trim_end());
-    result.push('\n');
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_ssh_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sshpod-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn renders_paths_relative_to_home() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            ssh_path(Path::new("/home/me/.cache/sshpod"), home),
+            "~/.cache/sshpod"
+        );
+        assert_eq!(
+            ssh_path(Path::new("/var/cache/sshpod"), home),
+            "/var/cache/sshpod"
+        );
+        let block = render_block("sshpod", "/data/my cache/sshpod");
+        assert!(block.contains("  IdentityFile \"/data/my cache/sshpod/id_ed25519\"\n"));
+    }
+
+    #[test]
+    fn detects_unmanaged_sshpod_blocks() {
+        let managed = render_block("sshpod", "~/.cache/sshpod");
+        let config = format!(
+            "Host *.sshpod\n  User root\n\nHost example\n  User me\n\n{}Host=*.sshpod\n  Port 22\n",
+            managed
+        );
+        assert_eq!(detect_conflicting_config(&config), ["1-2", "26-27"]);
+        assert!(detect_conflicting_config(&managed).is_empty());
+    }
+
+    #[test]
+    fn renders_block_options() {
+        let block = render_block_with_options(
+            "sshpod",
+            "~/.cache/sshpod",
+            &SshConfigOptions {
+                forward_agent: false,
+                server_alive_interval: 10,
+                identity_file: Some(PathBuf::from("~/.ssh/id_work")),
+                extra_options: vec![("LogLevel".into(), "ERROR".into())],
+                ..SshConfigOptions::default()
+            },
+        );
+        assert!(block.contains(
+            "  IdentityFile ~/.ssh/id_work\n  IdentityFile ~/.cache/sshpod/id_ed25519\n"
+        ));
+        assert!(block.contains("  ForwardAgent no\n  ServerAliveInterval 10\n"));
+        assert!(block.ends_with("  LogLevel ERROR\n# <<< sshpod end\n"));
+    }
+
+    #[test]
+    fn dry_run_writes_nothing() {
+        let dir = temp_ssh_dir("dry-run");
+        let config = "Host example\n  User me\n";
+        fs::write(dir.join("config"), config).unwrap();
+
+        configure_in(
+            &dir,
+            "~/.cache/sshpod",
+            "sshpod",
+            &SshConfigOptions::default(),
+            true,
+        )
+        .unwrap();
+        configure_in(
+            &dir.join("missing"),
+            "~/.cache/sshpod",
+            "sshpod",
+            &SshConfigOptions::default(),
+            true,
+        )
+        .unwrap();
+
+        let entries = fs::read_dir(&dir).unwrap().count();
+        let after = fs::read_to_string(dir.join("config")).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(after, config);
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    fn uninstall_without_block_is_noop() {
+        let dir = temp_ssh_dir("uninstall-noop");
+        let config = "Host example\n  User me\n\n";
+        fs::write(dir.join("config"), config).unwrap();
+
+        uninstall_in(&dir).unwrap();
+
+        let entries = fs::read_dir(&dir).unwrap().count();
+        let after = fs::read_to_string(dir.join("config")).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(after, config);
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    fn uninstall_removes_block() {
+        let dir = temp_ssh_dir("uninstall-block");
+        let config = merge_config(
+            "Host example\n  User me\n",
+            &render_block("sshpod", "~/.cache/sshpod"),
+        );
+        fs::write(dir.join("config"), config).unwrap();
+
+        uninstall_in(&dir).unwrap();
+
+        let after = fs::read_to_string(dir.join("config")).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(after, "Host example\n  User me\n");
+    }
+}
@@ -1,7 +1,10 @@
 use crate::paths;
-use anyhow::{Context, Result};
-use std::path::Path;
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 use tokio::fs;
 use tokio::process::Command;
 
@@ -10,14 +13,234 @@ pub struct Key {
     pub public: String,
 }
 
-pub async fn ensure_key(name: &str) -> Result<Key> {
-    let cache_dir = paths::home_dir()?.join(".cache/sshpod");
-    prepare_dir(&cache_dir, 0o700).await?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    Ed25519,
+    Rsa(u32),
+}
+
+impl KeyType {
+    pub fn client_key_name(self) -> &'static str {
+        match self {
+            KeyType::Ed25519 => "id_ed25519",
+            KeyType::Rsa(_) => "id_rsa",
+        }
+    }
+
+    fn keygen_args(self) -> Vec<String> {
+        match self {
+            KeyType::Ed25519 => vec!["-t".into(), "ed25519".into()],
+            KeyType::Rsa(bits) => vec!["-t".into(), "rsa".into(), "-b".into(), bits.to_string()],
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ed25519" => Ok(KeyType::Ed25519),
+            "rsa" => Ok(KeyType::Rsa(4096)),
+            other => bail!("unsupported key type '{}' (expected ed25519 or rsa)", other),
+        }
+    }
+}
+
+/// Fails early when ssh-keygen, used to create and inspect keys, is not installed.
+pub fn ensure_ssh_keygen() -> Result<()> {
+    if which::which("ssh-keygen").is_err() {
+        bail!("ssh-keygen not found in PATH. Install the OpenSSH client");
+    }
+    Ok(())
+}
+
+pub async fn ensure_key(name: &str, scope: Option<&str>) -> Result<Key> {
+    ensure_key_typed(name, KeyType::Ed25519, scope).await
+}
+
+pub async fn ensure_key_typed(name: &str, key_type: KeyType, scope: Option<&str>) -> Result<Key> {
+    let cache_dir = key_dir(scope)?;
+    ensure_key_in(&cache_dir, name, key_type).await
+}
+
+pub async fn generate_key(name: &str, key_type: KeyType) -> Result<Key> {
+    let cache_dir = key_dir(None)?;
+    let private_key = cache_dir.join(name);
+    for path in [private_key.clone(), private_key.with_extension("pub")] {
+        match fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to remove {}", path.display()))
+            }
+        }
+    }
+    ensure_key_in(&cache_dir, name, key_type).await
+}
 
+pub async fn fingerprint(path: &Path) -> Result<String> {
+    let output = Command::new("ssh-keygen")
+        .arg("-l")
+        .arg("-f")
+        .arg(path)
+        .output()
+        .await
+        .context("failed to spawn ssh-keygen")?;
+    if !output.status.success() {
+        bail!(
+            "ssh-keygen -l failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub async fn rotate_if_stale(
+    name: &str,
+    key_type: KeyType,
+    scope: Option<&str>,
+    max_age_days: u64,
+) -> Result<Key> {
+    let cache_dir = key_dir(scope)?;
+    rotate_in(&cache_dir, name, key_type, max_age_days).await
+}
+
+async fn rotate_in(
+    cache_dir: &Path,
+    name: &str,
+    key_type: KeyType,
+    max_age_days: u64,
+) -> Result<Key> {
+    let private_key = cache_dir.join(name);
+    if max_age_days > 0 && is_stale(&private_key, max_age_days).await {
+        let old_private = cache_dir.join(format!("{}.old", name));
+        let old_public = cache_dir.join(format!("{}.old.pub", name));
+        fs::rename(&private_key, &old_private)
+            .await
+            .with_context(|| format!("failed to move {} aside", private_key.display()))?;
+        let _ = fs::rename(private_key.with_extension("pub"), &old_public).await;
+        warn!(
+            "[sshpod] {} is older than {} days; generated a new key (previous key kept as {})",
+            private_key.display(),
+            max_age_days,
+            old_private.display()
+        );
+    }
+    ensure_key_in(cache_dir, name, key_type).await
+}
+
+async fn is_stale(path: &Path, max_age_days: u64) -> bool {
+    let Ok(modified) = fs::metadata(path).await.and_then(|m| m.modified()) else {
+        return false;
+    };
+    let max_age = Duration::from_secs(max_age_days * 24 * 60 * 60);
+    SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|age| age > max_age)
+}
+
+pub async fn import_existing(src_private: &Path) -> Result<Key> {
+    let cache_dir = key_dir(None)?;
+    import_into(&cache_dir, src_private).await
+}
+
+async fn import_into(cache_dir: &Path, src_private: &Path) -> Result<Key> {
+    let src_public = PathBuf::from(format!("{}.pub", src_private.display()));
+    let private = fs::read_to_string(src_private)
+        .await
+        .with_context(|| format!("failed to read {}", src_private.display()))?;
+    let public = fs::read_to_string(&src_public)
+        .await
+        .with_context(|| format!("failed to read {}", src_public.display()))?;
+
+    // Store the key under the name the generated ssh config already lists.
+    let name = if public.starts_with("ssh-ed25519 ") {
+        KeyType::Ed25519.client_key_name()
+    } else if public.starts_with("ssh-rsa ") {
+        KeyType::Rsa(0).client_key_name()
+    } else {
+        bail!(
+            "{} is not an ed25519 or rsa public key",
+            src_public.display()
+        );
+    };
+
+    prepare_dir(cache_dir, 0o700).await?;
     let private_key = cache_dir.join(name);
     let public_key = private_key.with_extension("pub");
+    let current = fs::read_to_string(&public_key).await.ok();
+    if current.as_deref() != Some(public.as_str()) {
+        if current.is_some() {
+            let _ = fs::rename(&private_key, cache_dir.join(format!("{}.old", name))).await;
+            let _ = fs::rename(&public_key, cache_dir.join(format!("{}.old.pub", name))).await;
+        }
+        write_private(&private_key, &private)
+            .await
+            .with_context(|| format!("failed to write {}", private_key.display()))?;
+        fs::write(&public_key, &public)
+            .await
+            .with_context(|| format!("failed to write {}", public_key.display()))?;
+        info!(
+            "[sshpod] imported {} as {}",
+            src_private.display(),
+            private_key.display()
+        );
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&private_key, std::fs::Permissions::from_mode(0o600)).await;
+        let _ = fs::set_permissions(&public_key, std::fs::Permissions::from_mode(0o600)).await;
+    }
+
+    Ok(Key { private, public })
+}
+
+// Creates the file owner-only from the start, so the key is never readable by others.
+async fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    // The mode only applies to new files.
+    let _ = fs::remove_file(path).await;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    file.write_all(contents.as_bytes()).await?;
+    file.flush().await
+}
 
-    ensure_ed25519_keys(&private_key)
+pub fn key_dir(scope: Option<&str>) -> Result<PathBuf> {
+    let base = paths::cache_dir()?;
+    Ok(match scope {
+        Some(scope) => base.join(sanitize_scope(scope)),
+        None => base,
+    })
+}
+
+// Context names can be ARNs or URLs; keep them to a single safe path segment.
+fn sanitize_scope(scope: &str) -> String {
+    scope
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+async fn ensure_key_in(cache_dir: &Path, name: &str, key_type: KeyType) -> Result<Key> {
+    prepare_dir(cache_dir, 0o700).await?;
+
+    let private_key = cache_dir.join(name);
+    let public_key = private_key.with_extension("pub");
+
+    ensure_keypair(&private_key, key_type)
         .await
         .with_context(|| format!("failed to create keypair {}", name))?;
 
@@ -43,26 +266,20 @@ async fn prepare_dir(path: &Path, mode: u32) -> Result<()> {
     Ok(())
 }
 
-async fn ensure_ed25519_keys(private_key: &Path) -> Result<()> {
+async fn ensure_keypair(private_key: &Path, key_type: KeyType) -> Result<()> {
     let public_key = private_key.with_extension("pub");
     if !private_key.exists() || !public_key.exists() {
         let status = Command::new("ssh-keygen")
-            .args([
-                "-q",
-                "-t",
-                "ed25519",
-                "-f",
-                private_key.to_str().unwrap_or_default(),
-                "-N",
-                "",
-            ])
+            .arg("-q")
+            .args(key_type.keygen_args())
+            .args(["-f", private_key.to_str().unwrap_or_default(), "-N", ""])
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .status()
             .await
             .context("failed to spawn ssh-keygen")?;
         if !status.success() {
-            anyhow::bail!("ssh-keygen failed with status {}", status);
+            bail!("ssh-keygen failed with status {}", status);
         }
     }
     #[cfg(unix)]
@@ -73,3 +290,86 @@ async fn ensure_ed25519_keys(private_key: &Path) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_type() {
+        assert_eq!("ed25519".parse::<KeyType>().unwrap(), KeyType::Ed25519);
+        assert_eq!("rsa".parse::<KeyType>().unwrap(), KeyType::Rsa(4096));
+        assert!("dsa".parse::<KeyType>().is_err());
+    }
+
+    #[test]
+    fn sanitizes_scope() {
+        assert_eq!(sanitize_scope("kind-dev"), "kind-dev");
+        assert_eq!(
+            sanitize_scope("arn:aws:eks:us-west-2:123:cluster/prod"),
+            "arn_aws_eks_us-west-2_123_cluster_prod"
+        );
+        assert_eq!(sanitize_scope(".."), "__");
+    }
+
+    #[tokio::test]
+    async fn rotates_stale_key() {
+        let dir = std::env::temp_dir().join(format!("sshpod-rotate-{}", std::process::id()));
+        let first = ensure_key_in(&dir, "id_ed25519", KeyType::Ed25519)
+            .await
+            .unwrap();
+
+        let fresh = rotate_in(&dir, "id_ed25519", KeyType::Ed25519, 1)
+            .await
+            .unwrap();
+        assert_eq!(fresh.public, first.public);
+
+        let old = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        std::fs::File::options()
+            .write(true)
+            .open(dir.join("id_ed25519"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let rotated = rotate_in(&dir, "id_ed25519", KeyType::Ed25519, 1)
+            .await
+            .unwrap();
+        let kept = std::fs::read_to_string(dir.join("id_ed25519.old.pub")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_ne!(rotated.public, first.public);
+        assert_eq!(kept, first.public);
+    }
+
+    #[tokio::test]
+    async fn imports_existing_key() {
+        let root = std::env::temp_dir().join(format!("sshpod-import-{}", std::process::id()));
+        let src = ensure_key_in(&root.join("src"), "mykey", KeyType::Ed25519)
+            .await
+            .unwrap();
+        let cache = root.join("cache");
+        let imported = import_into(&cache, &root.join("src/mykey")).await.unwrap();
+        let stored = std::fs::read_to_string(cache.join("id_ed25519.pub")).unwrap();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            let meta = std::fs::metadata(cache.join("id_ed25519")).unwrap();
+            meta.permissions().mode() & 0o777
+        };
+        let _ = std::fs::remove_dir_all(&root);
+        #[cfg(unix)]
+        assert_eq!(mode, 0o600);
+        assert_eq!(imported.public, src.public);
+        assert_eq!(stored, src.public);
+    }
+
+    #[tokio::test]
+    async fn generates_rsa_key() {
+        let dir = std::env::temp_dir().join(format!("sshpod-keys-{}", std::process::id()));
+        let key = ensure_key_in(&dir, "id_rsa", KeyType::Rsa(2048))
+            .await
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(key.public.starts_with("ssh-rsa "));
+        assert!(key.private.contains("PRIVATE KEY"));
+    }
+}
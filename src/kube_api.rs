@@ -0,0 +1,234 @@
+use crate::error::SshpodError;
+use crate::hostspec::encode_selector;
+use crate::kubectl::{retry_delay, KubectlBackend, KubectlOptions};
+use anyhow::{Context, Result};
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::{Client, Config};
+use log::warn;
+use serde::de::DeserializeOwned;
+use tokio::sync::OnceCell;
+use tokio::time::sleep;
+
+// Serves the `kubectl get ... -o json` lookups straight from the API server.
+// Anything else (config, exec, port-forward) still goes through kubectl.
+pub struct ApiBackend;
+
+// One invocation talks to one cluster, so the first options win.
+static CLIENT: OnceCell<Client> = OnceCell::const_new();
+
+impl KubectlBackend for ApiBackend {
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        opts: &KubectlOptions,
+        args: &[&str],
+        action: &str,
+    ) -> Result<T> {
+        let path =
+            api_path(args).with_context(|| format!("kubectl {} has no API equivalent", action))?;
+        let client = CLIENT.get_or_try_init(|| connect(opts)).await?;
+        let max_attempts = opts.retries.saturating_add(1);
+        let mut attempt = 1;
+        loop {
+            let request = http::Request::get(path.as_str()).body(Vec::new())?;
+            let err = match client.request(request).await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            if attempt >= max_attempts || !is_transient(&err) {
+                return Err(api_failure(err, args, action));
+            }
+            let delay = retry_delay(attempt);
+            warn!(
+                "[sshpod] API {} failed (attempt {}/{}), retrying in {}ms: {}",
+                action,
+                attempt,
+                max_attempts,
+                delay.as_millis(),
+                err
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+// The same retry policy as kubectl: throttling, unavailable servers and
+// connection failures are worth another attempt.
+fn is_transient(err: &kube::Error) -> bool {
+    match err {
+        kube::Error::Api(status) => matches!(status.code, 429 | 503 | 504),
+        kube::Error::HyperError(_) | kube::Error::Service(_) => true,
+        _ => false,
+    }
+}
+
+// Server-side errors become the same `KubectlFailed` kubectl would produce,
+// so callers' NotFound handling works for either backend.
+fn api_failure(err: kube::Error, args: &[&str], action: &str) -> anyhow::Error {
+    match err {
+        kube::Error::Api(status) => {
+            let stderr = format!("Error from server ({}): {}", status.reason, status.message);
+            SshpodError::kubectl_failed(args, &stderr).into()
+        }
+        err => anyhow::Error::new(err).context(format!("API {} failed", action)),
+    }
+}
+
+pub fn supports(args: &[&str]) -> bool {
+    api_path(args).is_some()
+}
+
+async fn connect(opts: &KubectlOptions) -> Result<Client> {
+    let options = KubeConfigOptions {
+        context: opts.context.clone(),
+        ..Default::default()
+    };
+    let mut config = match &opts.kubeconfig {
+        Some(path) => {
+            let kubeconfig = Kubeconfig::read_from(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            Config::from_custom_kubeconfig(kubeconfig, &options).await?
+        }
+        None if opts.context.is_some() => Config::from_kubeconfig(&options).await?,
+        None => Config::infer().await?,
+    };
+    if let Some(user) = &opts.impersonate {
+        config.auth_info.impersonate = Some(user.clone());
+    }
+    Client::try_from(config).context("failed to create Kubernetes API client")
+}
+
+// Maps `get <kind> [name] [-n ns] [-l selector] -o json` to a REST path.
+fn api_path(args: &[&str]) -> Option<String> {
+    let mut args = args.iter().copied();
+    if args.next()? != "get" {
+        return None;
+    }
+    let (prefix, resource, namespaced) = resource(args.next()?)?;
+    let (mut name, mut namespace, mut json) = (None, None, false);
+    let mut query = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg {
+            "-n" => namespace = Some(args.next()?),
+            "-l" => query.push(format!("labelSelector={}", encode_selector(args.next()?))),
+            "--field-selector" => {
+                query.push(format!("fieldSelector={}", encode_selector(args.next()?)))
+            }
+            "-o" => json = args.next()? == "json",
+            _ if !arg.starts_with('-') && name.is_none() => name = Some(arg),
+            _ => return None,
+        }
+    }
+    if !json {
+        return None;
+    }
+    let mut path = prefix.to_string();
+    match (namespaced, namespace) {
+        (true, Some(ns)) => path.push_str(&format!("/namespaces/{}", ns)),
+        // kubectl would fill in the context's namespace; leave that to it.
+        (true, None) | (false, Some(_)) => return None,
+        (false, None) => {}
+    }
+    path.push_str(&format!("/{}", resource));
+    if let Some(name) = name {
+        if !query.is_empty() {
+            return None;
+        }
+        path.push_str(&format!("/{}", name));
+    }
+    if !query.is_empty() {
+        path.push_str(&format!("?{}", query.join("&")));
+    }
+    Some(path)
+}
+
+fn resource(kind: &str) -> Option<(&'static str, &'static str, bool)> {
+    Some(match kind.trim_end_matches('s') {
+        "pod" => ("/api/v1", "pods", true),
+        "service" => ("/api/v1", "services", true),
+        "namespace" => ("/api/v1", "namespaces", false),
+        "deployment" => ("/apis/apps/v1", "deployments", true),
+        "statefulset" => ("/apis/apps/v1", "statefulsets", true),
+        "daemonset" => ("/apis/apps/v1", "daemonsets", true),
+        "replicaset" => ("/apis/apps/v1", "replicasets", true),
+        "job" => ("/apis/batch/v1", "jobs", true),
+        "cronjob" => ("/apis/batch/v1", "cronjobs", true),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::core::Status;
+
+    #[test]
+    fn maps_kubectl_gets_to_rest_paths() {
+        assert_eq!(
+            api_path(&["get", "deployment", "web", "-n", "prod", "-o", "json"]).as_deref(),
+            Some("/apis/apps/v1/namespaces/prod/deployments/web")
+        );
+        assert_eq!(
+            api_path(&[
+                "get",
+                "pods",
+                "-n",
+                "ci",
+                "-l",
+                "app=web,tier!=db",
+                "-o",
+                "json"
+            ])
+            .as_deref(),
+            Some("/api/v1/namespaces/ci/pods?labelSelector=app%3Dweb%2Ctier%21%3Ddb")
+        );
+        assert_eq!(
+            api_path(&[
+                "get",
+                "pods",
+                "-n",
+                "logging",
+                "-l",
+                "app=fluentd",
+                "--field-selector",
+                "spec.nodeName=worker-1",
+                "-o",
+                "json"
+            ])
+            .as_deref(),
+            Some(
+                "/api/v1/namespaces/logging/pods?labelSelector=app%3Dfluentd\
+                 &fieldSelector=spec%2EnodeName%3Dworker-1"
+            )
+        );
+        assert_eq!(
+            api_path(&["get", "namespaces", "-o", "json"]).as_deref(),
+            Some("/api/v1/namespaces")
+        );
+        assert_eq!(
+            api_path(&["get", "jobs", "-n", "etl", "-o", "json"]).as_deref(),
+            Some("/apis/batch/v1/namespaces/etl/jobs")
+        );
+        // Falls back to kubectl for anything it cannot express.
+        assert!(!supports(&["get", "pods", "-o", "json"]));
+        assert!(!supports(&["get", "pod", "web", "-n", "ci", "-o", "yaml"]));
+        assert!(!supports(&["get", "ingress", "-n", "ci", "-o", "json"]));
+        assert!(!supports(&["config", "view", "-o", "json"]));
+    }
+
+    #[test]
+    fn api_errors_match_kubectl_failures() {
+        let args = ["get", "pod", "web", "-n", "ci", "-o", "json"];
+        let not_found = Status::failure("pods \"web\" not found", "NotFound").with_code(404);
+        let err = api_failure(kube::Error::Api(not_found.boxed()), &args, "get pod");
+        assert!(matches!(
+            err.downcast_ref::<SshpodError>(),
+            Some(SshpodError::KubectlFailed { stderr, .. }) if stderr.contains("(NotFound)")
+        ));
+
+        let throttled = Status::failure("slow down", "TooManyRequests").with_code(429);
+        assert!(is_transient(&kube::Error::Api(throttled.boxed())));
+        let forbidden = Status::failure("no", "Forbidden").with_code(403);
+        assert!(!is_transient(&kube::Error::Api(forbidden.boxed())));
+    }
+}
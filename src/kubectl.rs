@@ -1,22 +1,221 @@
+use crate::error::SshpodError;
+use crate::kube_api;
+use crate::selector;
 use anyhow::{bail, Context, Result};
+use log::{info, warn};
 use serde::{de::DeserializeOwned, Deserialize};
 use std::collections::HashMap;
-use std::process::{Output, Stdio};
-use tokio::io::AsyncWriteExt;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::OnceLock;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::process::Command;
+use tokio::sync::OnceCell;
+use tokio::time::{sleep, Duration, Instant};
 
+const CP_RETRIES: u32 = 3;
+
+/// Global flags passed to every kubectl invocation.
+#[derive(Clone, Debug, Default)]
+pub struct KubectlOptions {
+    /// `--context`; the current context when unset.
+    pub context: Option<String>,
+    /// `--kubeconfig`; kubectl's own resolution when unset.
+    pub kubeconfig: Option<PathBuf>,
+    /// `--as`
+    pub impersonate: Option<String>,
+    /// Extra attempts for transient API failures.
+    pub retries: u32,
+}
+
+impl KubectlOptions {
+    pub(crate) fn command(&self) -> Command {
+        let mut cmd = self.config_command();
+        if let Some(user) = &self.impersonate {
+            cmd.arg("--as").arg(user);
+        }
+        if let Some(ctx) = &self.context {
+            cmd.arg("--context").arg(ctx);
+        }
+        cmd
+    }
+
+    fn config_command(&self) -> Command {
+        let mut cmd = Command::new(binary().program());
+        if let Some(path) = &self.kubeconfig {
+            cmd.arg("--kubeconfig").arg(path);
+        }
+        cmd
+    }
+}
+
+// `oc` is a drop-in replacement on OpenShift, where it may be the only CLI installed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KubectlBinary {
+    Kubectl,
+    Oc,
+    Path(PathBuf),
+}
+
+impl KubectlBinary {
+    fn program(&self) -> &OsStr {
+        match self {
+            KubectlBinary::Kubectl => OsStr::new("kubectl"),
+            KubectlBinary::Oc => OsStr::new("oc"),
+            KubectlBinary::Path(path) => path.as_os_str(),
+        }
+    }
+}
+
+static BINARY: OnceLock<KubectlBinary> = OnceLock::new();
+
+pub fn init_binary(path: Option<PathBuf>) {
+    let binary = match path {
+        Some(path) => KubectlBinary::Path(path),
+        None => detect_binary(std::env::var_os("PATH")),
+    };
+    let _ = BINARY.set(binary);
+}
+
+fn binary() -> &'static KubectlBinary {
+    BINARY.get_or_init(|| detect_binary(std::env::var_os("PATH")))
+}
+
+/// Fails with install instructions when the kubectl binary in use cannot be found.
+pub(crate) fn ensure_binary() -> Result<()> {
+    locate(binary()).map(|_| ())
+}
+
+fn locate(binary: &KubectlBinary) -> Result<PathBuf> {
+    match (which::which(binary.program()), binary) {
+        (Ok(path), _) => Ok(path),
+        (Err(_), KubectlBinary::Path(path)) => bail!("kubectl binary {} not found", path.display()),
+        (Err(_), _) => bail!(
+            "{} not found in PATH. Install kubectl from https://kubernetes.io/docs/tasks/tools/",
+            binary.program().to_string_lossy()
+        ),
+    }
+}
+
+// Prefer kubectl, fall back to oc; with neither, keep kubectl so errors name it.
+fn detect_binary(path_var: Option<OsString>) -> KubectlBinary {
+    let dirs: Vec<PathBuf> = path_var
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    let found = |name: &str| {
+        let file = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+        dirs.iter().any(|dir| dir.join(&file).is_file())
+    };
+    if !found("kubectl") && found("oc") {
+        KubectlBinary::Oc
+    } else {
+        KubectlBinary::Kubectl
+    }
+}
+
+/// A resolved container that commands are run in.
 #[derive(Clone, Debug)]
 pub struct RemoteTarget {
-    pub context: Option<String>,
+    /// How to reach the cluster.
+    pub opts: KubectlOptions,
+    /// Namespace of the pod.
     pub namespace: String,
+    /// Pod name.
     pub pod: String,
+    /// Container name.
     pub container: String,
+    /// Whether `container` is an init container.
+    pub init_container: bool,
+}
+
+impl RemoteTarget {
+    /// Starts building a target; namespace, pod and container are required.
+    pub fn builder() -> RemoteTargetBuilder {
+        RemoteTargetBuilder::default()
+    }
+}
+
+/// Builder for [`RemoteTarget`].
+#[derive(Clone, Debug, Default)]
+pub struct RemoteTargetBuilder {
+    opts: KubectlOptions,
+    namespace: Option<String>,
+    pod: Option<String>,
+    container: Option<String>,
+    init_container: bool,
+}
+
+impl RemoteTargetBuilder {
+    /// Replaces all kubectl flags at once.
+    pub fn options(mut self, opts: KubectlOptions) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// Sets `--context`.
+    pub fn context(mut self, context: Option<String>) -> Self {
+        self.opts.context = context;
+        self
+    }
+
+    /// Sets `--kubeconfig`.
+    pub fn kubeconfig(mut self, kubeconfig: Option<PathBuf>) -> Self {
+        self.opts.kubeconfig = kubeconfig;
+        self
+    }
+
+    /// Sets the pod's namespace.
+    pub fn namespace(mut self, namespace: String) -> Self {
+        self.namespace = Some(namespace);
+        self
+    }
+
+    /// Sets the pod name.
+    pub fn pod(mut self, pod: String) -> Self {
+        self.pod = Some(pod);
+        self
+    }
+
+    /// Sets the container name.
+    pub fn container(mut self, container: String) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Marks the container as an init container.
+    pub fn init_container(mut self, init_container: bool) -> Self {
+        self.init_container = init_container;
+        self
+    }
+
+    /// Fails if namespace, pod or container is missing or empty.
+    pub fn build(self) -> Result<RemoteTarget> {
+        let required = |value: Option<String>, what: &str| match value {
+            Some(value) if !value.is_empty() => Ok(value),
+            _ => bail!("remote target is missing its {}", what),
+        };
+        Ok(RemoteTarget {
+            opts: self.opts,
+            namespace: required(self.namespace, "namespace")?,
+            pod: required(self.pod, "pod")?,
+            container: required(self.container, "container")?,
+            init_container: self.init_container,
+        })
+    }
 }
 
+/// The parts of a pod sshpod needs.
 #[derive(Debug, Clone)]
 pub struct PodInfo {
+    /// Pod name.
+    pub name: String,
+    /// Pod UID; part of the install path, so a recreated pod starts fresh.
     pub uid: String,
+    /// Names of the regular containers.
     pub containers: Vec<String>,
+    /// Names of the init containers.
+    pub init_containers: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -27,12 +226,15 @@ struct Pod {
 
 #[derive(Deserialize)]
 struct PodMetadata {
+    name: String,
     uid: String,
 }
 
 #[derive(Deserialize)]
 struct PodSpec {
     containers: Vec<ContainerSpec>,
+    #[serde(default, rename = "initContainers")]
+    init_containers: Vec<ContainerSpec>,
 }
 
 #[derive(Deserialize)]
@@ -66,6 +268,36 @@ struct DeploymentSpec {
     selector: LabelSelector,
 }
 
+#[derive(Deserialize)]
+struct ReplicaSet {
+    spec: ReplicaSetSpec,
+}
+
+#[derive(Deserialize)]
+struct ReplicaSetSpec {
+    selector: LabelSelector,
+}
+
+#[derive(Deserialize)]
+struct StatefulSet {
+    spec: StatefulSetSpec,
+}
+
+#[derive(Deserialize)]
+struct StatefulSetSpec {
+    selector: LabelSelector,
+}
+
+#[derive(Deserialize)]
+struct DaemonSet {
+    spec: DaemonSetSpec,
+}
+
+#[derive(Deserialize)]
+struct DaemonSetSpec {
+    selector: LabelSelector,
+}
+
 #[derive(Deserialize)]
 struct Job {
     spec: JobSpec,
@@ -89,21 +321,90 @@ struct PodTemplateMetadata {
     labels: HashMap<String, String>,
 }
 
+#[derive(Deserialize)]
+struct PodInfoList {
+    items: Vec<Pod>,
+}
+
+#[derive(Deserialize)]
+struct AllPodList {
+    items: Vec<AllPodItem>,
+}
+
+#[derive(Deserialize)]
+struct AllPodItem {
+    metadata: AllPodMetadata,
+    spec: PodSpec,
+    #[serde(default)]
+    status: Option<PodStatus>,
+}
+
+#[derive(Deserialize)]
+struct AllPodMetadata {
+    name: String,
+    uid: String,
+    namespace: String,
+}
+
+impl AllPodItem {
+    fn into_info(self) -> (String, PodInfo) {
+        let pod = Pod {
+            metadata: PodMetadata {
+                name: self.metadata.name,
+                uid: self.metadata.uid,
+            },
+            spec: self.spec,
+        };
+        (self.metadata.namespace, pod_info_from(pod))
+    }
+}
+
+#[derive(Deserialize)]
+struct NamespaceList {
+    items: Vec<NamespaceItem>,
+}
+
+#[derive(Deserialize)]
+struct NamespaceItem {
+    metadata: PodMetadataName,
+}
+
 #[derive(Deserialize)]
 struct PodList {
     items: Vec<PodListItem>,
 }
 
 #[derive(Deserialize)]
-struct PodListItem {
+pub(crate) struct PodListItem {
     metadata: PodMetadataName,
     #[serde(default)]
     status: Option<PodStatus>,
 }
 
+impl PodListItem {
+    pub(crate) fn is_ready(&self) -> bool {
+        status_ready(self.status.as_ref())
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.status
+            .as_ref()
+            .and_then(|s| s.phase.as_ref())
+            .map(|p| p == "Running")
+            .unwrap_or(false)
+    }
+
+    // RFC 3339 in UTC, so the strings order chronologically.
+    pub(crate) fn creation_timestamp(&self) -> Option<&str> {
+        self.metadata.creation_timestamp.as_deref()
+    }
+}
+
 #[derive(Deserialize)]
 struct PodMetadataName {
     name: String,
+    #[serde(default, rename = "creationTimestamp")]
+    creation_timestamp: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -133,14 +434,128 @@ struct DeploymentItem {
     status: Option<DeploymentStatus>,
 }
 
-#[derive(Deserialize)]
-struct DeploymentStatus {
+/// Replica counts from a Deployment's status.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DeploymentStatus {
+    /// Pods the Deployment currently targets.
+    #[serde(default)]
+    pub replicas: Option<u32>,
+    /// Pods available for at least minReadySeconds.
     #[serde(default, rename = "availableReplicas")]
-    available_replicas: Option<u32>,
+    pub available_replicas: Option<u32>,
+    /// Pods passing their readiness probe.
+    #[serde(default, rename = "readyReplicas")]
+    pub ready_replicas: Option<u32>,
+}
+
+impl DeploymentStatus {
+    fn describe(&self, deployment: &str) -> String {
+        format!(
+            "deployment `{}` has {}/{} ready replicas",
+            deployment,
+            self.ready_replicas.unwrap_or(0),
+            self.replicas.unwrap_or(0)
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct StatefulSetList {
+    items: Vec<StatefulSetItem>,
+}
+
+#[derive(Deserialize)]
+struct StatefulSetItem {
+    metadata: PodMetadataName,
+    #[serde(default)]
+    status: Option<StatefulSetStatus>,
+}
+
+#[derive(Deserialize)]
+struct StatefulSetStatus {
     #[serde(default, rename = "readyReplicas")]
     ready_replicas: Option<u32>,
 }
 
+#[derive(Deserialize)]
+struct DaemonSetList {
+    items: Vec<DaemonSetItem>,
+}
+
+#[derive(Deserialize)]
+struct DaemonSetItem {
+    metadata: PodMetadataName,
+    #[serde(default)]
+    status: Option<DaemonSetStatus>,
+}
+
+#[derive(Deserialize)]
+struct DaemonSetStatus {
+    #[serde(default, rename = "numberReady")]
+    number_ready: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ConfigView {
+    #[serde(default)]
+    contexts: Vec<NamedContext>,
+}
+
+#[derive(Deserialize)]
+struct NamedContext {
+    name: String,
+    context: ContextEntry,
+}
+
+#[derive(Deserialize)]
+struct ContextEntry {
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Service {
+    spec: ServiceSpec,
+}
+
+// Services carry a plain label map, not a LabelSelector.
+#[derive(Deserialize)]
+struct ServiceSpec {
+    #[serde(default)]
+    selector: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct CronJob {
+    metadata: PodMetadata,
+}
+
+#[derive(Deserialize)]
+struct OwnedJobList {
+    items: Vec<OwnedJobItem>,
+}
+
+#[derive(Deserialize)]
+struct OwnedJobItem {
+    metadata: OwnedJobMetadata,
+    #[serde(default)]
+    status: Option<JobStatus>,
+}
+
+#[derive(Deserialize)]
+struct OwnedJobMetadata {
+    name: String,
+    #[serde(default, rename = "creationTimestamp")]
+    creation_timestamp: Option<String>,
+    #[serde(default, rename = "ownerReferences")]
+    owner_references: Vec<OwnerReference>,
+}
+
+#[derive(Deserialize)]
+struct OwnerReference {
+    uid: String,
+}
+
 #[derive(Deserialize)]
 struct JobList {
     items: Vec<JobItem>,
@@ -153,68 +568,237 @@ struct JobItem {
     status: Option<JobStatus>,
 }
 
-#[derive(Deserialize)]
-struct JobStatus {
+/// Pod counts from a Job's status.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct JobStatus {
+    /// Pods that completed successfully.
+    #[serde(default)]
+    pub succeeded: Option<u32>,
+    /// Pods still running or pending.
     #[serde(default)]
-    succeeded: Option<u32>,
+    pub active: Option<u32>,
+    /// Active pods that are Ready.
     #[serde(default)]
-    active: Option<u32>,
+    pub ready: Option<u32>,
+    /// Pods that failed.
     #[serde(default)]
-    ready: Option<u32>,
+    pub failed: Option<u32>,
 }
 
-fn kubectl_base(context: Option<&str>) -> Command {
-    let mut cmd = Command::new("kubectl");
-    if let Some(ctx) = context {
-        cmd.arg("--context").arg(ctx);
+impl JobStatus {
+    fn describe(&self, job: &str) -> String {
+        let (succeeded, active, failed) = (
+            self.succeeded.unwrap_or(0),
+            self.active.unwrap_or(0),
+            self.failed.unwrap_or(0),
+        );
+        let state = if active > 0 {
+            format!("no Ready pods yet (active: {})", active)
+        } else if succeeded > 0 {
+            format!("completed (succeeded: {}, active: {})", succeeded, active)
+        } else if failed > 0 {
+            format!("failed (failed: {}, active: {})", failed, active)
+        } else {
+            "not started any pods".to_string()
+        };
+        format!(
+            "job `{}` has {}, no running pods available for SSH",
+            job, state
+        )
     }
-    cmd
+}
+
+#[derive(Deserialize)]
+struct VersionInfo {
+    #[serde(rename = "clientVersion")]
+    client_version: ClientVersion,
+}
+
+#[derive(Deserialize)]
+struct ClientVersion {
+    #[serde(rename = "gitVersion")]
+    git_version: String,
+}
+
+// Where `kubectl get ... -o json` lookups are answered from.
+pub trait KubectlBackend {
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        opts: &KubectlOptions,
+        args: &[&str],
+        action: &str,
+    ) -> Result<T>;
+}
+
+pub struct ProcessBackend;
+
+impl KubectlBackend for ProcessBackend {
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        opts: &KubectlOptions,
+        args: &[&str],
+        action: &str,
+    ) -> Result<T> {
+        run_kubectl_json_with_retry(opts, args, action, opts.retries.saturating_add(1)).await
+    }
+}
+
+static USE_KUBE_API: OnceLock<bool> = OnceLock::new();
+
+pub fn init_backend(use_kube_api: bool) {
+    let _ = USE_KUBE_API.set(use_kube_api);
 }
 
 async fn run_kubectl_json<T: DeserializeOwned>(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     args: &[&str],
     action: &str,
 ) -> Result<T> {
-    let output = kubectl_base(context)
-        .args(args)
-        .output()
-        .await
-        .with_context(|| format!("failed to run kubectl {}", action))?;
-    if !output.status.success() {
-        bail!(
-            "kubectl {} failed: {}",
+    if USE_KUBE_API.get().copied().unwrap_or(false) && kube_api::supports(args) {
+        return kube_api::ApiBackend.get_json(opts, args, action).await;
+    }
+    ProcessBackend.get_json(opts, args, action).await
+}
+
+async fn run_kubectl_json_with_retry<T: DeserializeOwned>(
+    opts: &KubectlOptions,
+    args: &[&str],
+    action: &str,
+    max_attempts: u32,
+) -> Result<T> {
+    let mut attempt = 1;
+    let output = loop {
+        let output = opts
+            .command()
+            .args(args)
+            .output()
+            .await
+            .with_context(|| format!("failed to run kubectl {}", action))?;
+        if output.status.success() {
+            break output;
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if attempt >= max_attempts || !is_transient_failure(&stderr) {
+            return Err(SshpodError::kubectl_failed(args, &stderr).into());
+        }
+        let delay = retry_delay(attempt);
+        warn!(
+            "[sshpod] kubectl {} failed (attempt {}/{}), retrying in {}ms: {}",
             action,
-            String::from_utf8_lossy(&output.stderr).trim()
+            attempt,
+            max_attempts,
+            delay.as_millis(),
+            stderr.trim()
         );
-    }
+        sleep(delay).await;
+        attempt += 1;
+    };
     serde_json::from_slice(&output.stdout)
         .with_context(|| format!("failed to parse kubectl {} json output", action))
 }
 
+fn is_transient_failure(stderr: &str) -> bool {
+    let lower = stderr.to_ascii_lowercase();
+    [
+        "unable to connect",
+        "timeout",
+        "too many requests",
+        "service unavailable",
+        "429",
+        "503",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+}
+
+pub(crate) fn retry_delay(attempt: u32) -> Duration {
+    let millis = 500u64.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    Duration::from_millis(millis.min(10_000))
+}
+
 async fn fetch_with_ready_list<T: DeserializeOwned>(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     kind: &str,
     args: &[&str],
     action: &str,
 ) -> Result<T> {
-    match run_kubectl_json(context, args, action).await {
-        Ok(value) => Ok(value),
-        Err(err) => {
-            let mut message = err.to_string();
-            if let Ok(list) = list_resources(context, namespace, kind).await {
-                if !list.is_empty() {
-                    message.push_str(&format!(" Ready {kind}s: {}", list.join(", ")));
-                }
-            }
-            bail!(message);
+    let err = match run_kubectl_json(opts, args, action).await {
+        Ok(value) => return Ok(value),
+        Err(err) => not_found_as_pod_error(err, namespace, kind, args),
+    };
+    match list_resources(opts, namespace, kind, None).await {
+        Ok(list) if !list.is_empty() => Err(err.context(format!(
+            "failed to {} (Ready {kind}s: {})",
+            action,
+            list.join(", ")
+        ))),
+        _ => Err(err),
+    }
+}
+
+fn not_found_as_pod_error(
+    err: anyhow::Error,
+    namespace: &str,
+    kind: &str,
+    args: &[&str],
+) -> anyhow::Error {
+    let not_found = matches!(
+        err.downcast_ref::<SshpodError>(),
+        Some(SshpodError::KubectlFailed { stderr, .. }) if stderr.contains("(NotFound)")
+    );
+    match (kind, args) {
+        ("pod", ["get", "pod", name, ..]) if not_found => SshpodError::PodNotFound {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+        .into(),
+        _ => err,
+    }
+}
+
+// Stdout and stderr together, whatever the exit status; meant for reports.
+pub async fn capture_combined(opts: &KubectlOptions, args: &[&str]) -> Result<String> {
+    let output = opts
+        .command()
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("failed to run kubectl {}", args.join(" ")))?;
+    let mut text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        if !text.is_empty() {
+            text.push('\n');
         }
+        text.push_str(stderr.trim());
+    }
+    if !output.status.success() {
+        text.push_str(&format!("\n({})", output.status));
+    }
+    Ok(text)
+}
+
+pub async fn client_version(opts: &KubectlOptions) -> Result<String> {
+    let output = opts
+        .config_command()
+        .args(["version", "--client", "-o", "json"])
+        .output()
+        .await
+        .context("failed to run kubectl; is it installed and on PATH?")?;
+    if !output.status.success() {
+        bail!(
+            "kubectl version failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
+    let version: VersionInfo =
+        serde_json::from_slice(&output.stdout).context("failed to parse kubectl version")?;
+    Ok(version.client_version.git_version)
 }
 
-pub async fn ensure_context_exists(context: &str) -> Result<()> {
-    let contexts = list_contexts().await?;
+pub async fn ensure_context_exists(context: &str, opts: &KubectlOptions) -> Result<()> {
+    let contexts = list_contexts(opts).await?;
     if contexts.iter().any(|c| c == context) {
         return Ok(());
     }
@@ -225,8 +809,21 @@ pub async fn ensure_context_exists(context: &str) -> Result<()> {
     );
 }
 
-pub async fn list_contexts() -> Result<Vec<String>> {
-    let output = Command::new("kubectl")
+// kubeconfig rarely changes during one invocation, so these lookups are
+// cached for the life of the process.
+static CONTEXTS: OnceCell<Vec<String>> = OnceCell::const_new();
+static CONTEXT_NAMESPACES: OnceCell<HashMap<String, Option<String>>> = OnceCell::const_new();
+
+pub async fn list_contexts(opts: &KubectlOptions) -> Result<Vec<String>> {
+    CONTEXTS
+        .get_or_try_init(|| fetch_contexts(opts))
+        .await
+        .cloned()
+}
+
+async fn fetch_contexts(opts: &KubectlOptions) -> Result<Vec<String>> {
+    let output = opts
+        .config_command()
         .args(["config", "get-contexts", "-o", "name"])
         .output()
         .await
@@ -245,17 +842,20 @@ pub async fn list_contexts() -> Result<Vec<String>> {
     Ok(list)
 }
 
-pub async fn get_context_namespace(context: &str) -> Result<Option<String>> {
-    let output = Command::new("kubectl")
-        .args([
-            "config",
-            "view",
-            "-o",
-            &format!(
-                "jsonpath={{.contexts[?(@.name==\"{}\")].context.namespace}}",
-                context
-            ),
-        ])
+pub async fn get_context_namespace(context: &str, opts: &KubectlOptions) -> Result<Option<String>> {
+    let namespaces = CONTEXT_NAMESPACES
+        .get_or_try_init(|| fetch_context_namespaces(opts))
+        .await?;
+    Ok(namespaces.get(context).cloned().flatten())
+}
+
+// One `config view` answers the namespace of every context at once.
+async fn fetch_context_namespaces(
+    opts: &KubectlOptions,
+) -> Result<HashMap<String, Option<String>>> {
+    let output = opts
+        .config_command()
+        .args(["config", "view", "-o", "json"])
         .output()
         .await
         .context("failed to run kubectl config view")?;
@@ -265,17 +865,35 @@ pub async fn get_context_namespace(context: &str) -> Result<Option<String>> {
             String::from_utf8_lossy(&output.stderr).trim()
         );
     }
-    let ns = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if ns.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(ns))
+    parse_context_namespaces(&output.stdout)
+}
+
+fn parse_context_namespaces(json: &[u8]) -> Result<HashMap<String, Option<String>>> {
+    let view: ConfigView =
+        serde_json::from_slice(json).context("failed to parse kubectl config view output")?;
+    Ok(view
+        .contexts
+        .into_iter()
+        .map(|c| (c.name, c.context.namespace.filter(|ns| !ns.is_empty())))
+        .collect())
+}
+
+pub async fn resolve_namespace(
+    opts: &KubectlOptions,
+    namespace: Option<&str>,
+    context: Option<&str>,
+) -> Result<String> {
+    if let Some(ns) = namespace {
+        return Ok(ns.to_string());
     }
+    Ok(get_context_namespace(context.unwrap_or("default"), opts)
+        .await?
+        .unwrap_or_default())
 }
 
-pub async fn get_pod_info(context: Option<&str>, namespace: &str, pod: &str) -> Result<PodInfo> {
+pub async fn get_pod_info(opts: &KubectlOptions, namespace: &str, pod: &str) -> Result<PodInfo> {
     let parsed: Pod = fetch_with_ready_list(
-        context,
+        opts,
         namespace,
         "pod",
         &["get", "pod", pod, "-n", namespace, "-o", "json"],
@@ -283,44 +901,276 @@ pub async fn get_pod_info(context: Option<&str>, namespace: &str, pod: &str) ->
     )
     .await?;
 
-    Ok(PodInfo {
-        uid: parsed.metadata.uid,
-        containers: parsed.spec.containers.into_iter().map(|c| c.name).collect(),
-    })
+    Ok(pod_info_from(parsed))
+}
+
+fn pod_info_from(pod: Pod) -> PodInfo {
+    PodInfo {
+        name: pod.metadata.name,
+        uid: pod.metadata.uid,
+        containers: pod.spec.containers.into_iter().map(|c| c.name).collect(),
+        init_containers: pod
+            .spec
+            .init_containers
+            .into_iter()
+            .map(|c| c.name)
+            .collect(),
+    }
+}
+
+// Pods in every namespace as (namespace, info), Ready ones only unless asked.
+pub async fn list_all_pods(
+    opts: &KubectlOptions,
+    include_non_ready: bool,
+) -> Result<Vec<(String, PodInfo)>> {
+    let list: AllPodList = run_kubectl_json(
+        opts,
+        &["get", "pods", "--all-namespaces", "-o", "json"],
+        "get pods --all-namespaces",
+    )
+    .await?;
+    Ok(list
+        .items
+        .into_iter()
+        .filter(|item| include_non_ready || status_ready(item.status.as_ref()))
+        .map(AllPodItem::into_info)
+        .collect())
+}
+
+/// Names of all namespaces visible with `opts`.
+pub async fn list_namespaces(opts: &KubectlOptions) -> Result<Vec<String>> {
+    let list: NamespaceList =
+        run_kubectl_json(opts, &["get", "namespaces", "-o", "json"], "get namespaces").await?;
+    Ok(list.items.into_iter().map(|n| n.metadata.name).collect())
+}
+
+/// Every pod in `namespace`, Ready or not.
+pub async fn list_pods_in_namespace(
+    opts: &KubectlOptions,
+    namespace: &str,
+) -> Result<Vec<PodInfo>> {
+    let list: PodInfoList = run_kubectl_json(
+        opts,
+        &["get", "pods", "-n", namespace, "-o", "json"],
+        "get pods",
+    )
+    .await?;
+    Ok(list.items.into_iter().map(pod_info_from).collect())
+}
+
+pub async fn wait_for_pod_ready(
+    opts: &KubectlOptions,
+    namespace: &str,
+    pod: &str,
+    timeout_secs: u64,
+) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    loop {
+        let state = match run_kubectl_json::<PodListItem>(
+            opts,
+            &["get", "pod", pod, "-n", namespace, "-o", "json"],
+            "get pod",
+        )
+        .await
+        {
+            Ok(item) if item.is_ready() => return Ok(()),
+            Ok(item) => format!(
+                "phase {}",
+                item.status
+                    .and_then(|s| s.phase)
+                    .unwrap_or_else(|| "Unknown".to_string())
+            ),
+            Err(err) => err.to_string(),
+        };
+        if Instant::now() >= deadline {
+            bail!(
+                "pod {} in namespace {} did not become ready within {}s (last state: {})",
+                pod,
+                namespace,
+                timeout_secs,
+                state
+            );
+        }
+        info!(
+            "[sshpod] waiting for pod {} to become ready ({})",
+            pod, state
+        );
+        sleep(Duration::from_secs(2)).await;
+    }
 }
 
 pub async fn choose_pod_for_deployment(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     deployment: &str,
 ) -> Result<String> {
     let deploy: Deployment = fetch_with_ready_list(
-        context,
+        opts,
+        namespace,
+        "deployment",
+        &[
+            "get",
+            "deployment",
+            deployment,
+            "-n",
+            namespace,
+            "-o",
+            "json",
+        ],
+        &format!("get deployment {}", deployment),
+    )
+    .await?;
+    let selector = to_selector(&deploy.spec.selector)?;
+    match select_pod(opts, namespace, &selector, "deployment").await {
+        Ok(pod) => Ok(pod),
+        Err(err) => match get_deployment_status(opts, namespace, deployment).await {
+            Ok(status) => Err(err.context(status.describe(deployment))),
+            Err(_) => Err(err),
+        },
+    }
+}
+
+/// Fetches the replica counts of `deployment`.
+pub async fn get_deployment_status(
+    opts: &KubectlOptions,
+    namespace: &str,
+    deployment: &str,
+) -> Result<DeploymentStatus> {
+    let item: DeploymentItem = run_kubectl_json(
+        opts,
+        &[
+            "get",
+            "deployment",
+            deployment,
+            "-n",
+            namespace,
+            "-o",
+            "json",
+        ],
+        &format!("get deployment {}", deployment),
+    )
+    .await?;
+    Ok(item.status.unwrap_or_default())
+}
+
+pub async fn choose_pod_for_replicaset(
+    opts: &KubectlOptions,
+    namespace: &str,
+    replicaset: &str,
+) -> Result<String> {
+    warn!(
+        "[sshpod] targeting replicaset `{}` directly; ReplicaSets are usually owned by a Deployment, consider deployment--<name>",
+        replicaset
+    );
+    let rs: ReplicaSet = fetch_with_ready_list(
+        opts,
+        namespace,
+        "replicaset",
+        &[
+            "get",
+            "replicaset",
+            replicaset,
+            "-n",
+            namespace,
+            "-o",
+            "json",
+        ],
+        &format!("get replicaset {}", replicaset),
+    )
+    .await?;
+    let selector = to_selector(&rs.spec.selector)?;
+    select_pod(opts, namespace, &selector, "replicaset").await
+}
+
+pub async fn choose_pod_for_statefulset(
+    opts: &KubectlOptions,
+    namespace: &str,
+    statefulset: &str,
+) -> Result<String> {
+    let sts: StatefulSet = fetch_with_ready_list(
+        opts,
+        namespace,
+        "statefulset",
+        &[
+            "get",
+            "statefulset",
+            statefulset,
+            "-n",
+            namespace,
+            "-o",
+            "json",
+        ],
+        &format!("get statefulset {}", statefulset),
+    )
+    .await?;
+    let selector = to_selector(&sts.spec.selector)?;
+    let mut pods = fetch_pods(opts, namespace, &selector, None).await?;
+    if pods.items.is_empty() {
+        bail!(
+            "no pods found for statefulset selector `{}` in namespace {}",
+            selector,
+            namespace
+        );
+    }
+    sort_by_ordinal(&mut pods.items);
+    if let Some(p) = pick_pod(&pods.items) {
+        return Ok(p.metadata.name.clone());
+    }
+    bail!(
+        "no suitable pods found for statefulset selector `{}` in namespace {}",
+        selector,
+        namespace
+    );
+}
+
+fn sort_by_ordinal(pods: &mut [PodListItem]) {
+    pods.sort_by_key(|p| pod_ordinal(&p.metadata.name).unwrap_or(u32::MAX));
+}
+
+fn pod_ordinal(name: &str) -> Option<u32> {
+    let (_, suffix) = name.rsplit_once('-')?;
+    suffix.parse().ok()
+}
+
+pub async fn choose_pod_for_daemonset(
+    opts: &KubectlOptions,
+    namespace: &str,
+    daemonset: &str,
+    node: Option<&str>,
+) -> Result<String> {
+    let ds: DaemonSet = fetch_with_ready_list(
+        opts,
         namespace,
-        "deployment",
-        &[
-            "get",
-            "deployment",
-            deployment,
-            "-n",
-            namespace,
-            "-o",
-            "json",
-        ],
-        &format!("get deployment {}", deployment),
+        "daemonset",
+        &["get", "daemonset", daemonset, "-n", namespace, "-o", "json"],
+        &format!("get daemonset {}", daemonset),
     )
     .await?;
-    let selector = to_selector(&deploy.spec.selector)?;
-    select_pod(context, namespace, &selector, "deployment").await
+    let selector = to_selector(&ds.spec.selector)?;
+    let node = match node {
+        Some(node) => node,
+        None => return select_pod(opts, namespace, &selector, "daemonset").await,
+    };
+    let on_node = node_field_selector(node);
+    let pods = fetch_pods(opts, namespace, &selector, Some(&on_node)).await?;
+    if let Some(p) = pick_pod(&pods.items) {
+        return Ok(p.metadata.name.clone());
+    }
+    bail!(
+        "no pods found for daemonset selector `{}` on node {} in namespace {}",
+        selector,
+        node,
+        namespace
+    );
 }
 
 pub async fn choose_pod_for_job(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     job: &str,
 ) -> Result<String> {
     let job_spec: Job = fetch_with_ready_list(
-        context,
+        opts,
         namespace,
         "job",
         &["get", "job", job, "-n", namespace, "-o", "json"],
@@ -341,21 +1191,132 @@ pub async fn choose_pod_for_job(
     } else {
         format!("job-name={}", job)
     };
-    select_pod(context, namespace, &selector, "job").await
+    match select_pod(opts, namespace, &selector, "job").await {
+        Ok(pod) => Ok(pod),
+        Err(err) => match get_job_status(opts, namespace, job).await {
+            Ok(status) => Err(err.context(status.describe(job))),
+            Err(_) => Err(err),
+        },
+    }
 }
 
-async fn select_pod(
-    context: Option<&str>,
+/// Fetches the succeeded/active/failed pod counts of `job`.
+pub async fn get_job_status(
+    opts: &KubectlOptions,
+    namespace: &str,
+    job: &str,
+) -> Result<JobStatus> {
+    let item: JobItem = run_kubectl_json(
+        opts,
+        &["get", "job", job, "-n", namespace, "-o", "json"],
+        &format!("get job {}", job),
+    )
+    .await?;
+    Ok(item.status.unwrap_or_default())
+}
+
+pub async fn choose_pod_for_label_selector(
+    opts: &KubectlOptions,
     namespace: &str,
     selector: &str,
-    kind: &str,
 ) -> Result<String> {
-    let pods: PodList = run_kubectl_json(
-        context,
-        &["get", "pods", "-n", namespace, "-l", selector, "-o", "json"],
-        "get pods",
+    select_pod(opts, namespace, selector, "label").await
+}
+
+pub async fn choose_pod_for_service(
+    opts: &KubectlOptions,
+    namespace: &str,
+    service: &str,
+) -> Result<String> {
+    let svc: Service = run_kubectl_json(
+        opts,
+        &["get", "service", service, "-n", namespace, "-o", "json"],
+        &format!("get service {}", service),
+    )
+    .await?;
+    let selector = service_selector(&svc.spec);
+    if selector.is_empty() {
+        bail!(
+            "service `{}` in namespace {} has no selector; target its pods directly",
+            service,
+            namespace
+        );
+    }
+    select_pod(opts, namespace, &selector, "service").await
+}
+
+fn service_selector(spec: &ServiceSpec) -> String {
+    let mut parts: Vec<String> = spec
+        .selector
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect();
+    parts.sort();
+    parts.join(",")
+}
+
+pub async fn choose_pod_for_cronjob(
+    opts: &KubectlOptions,
+    namespace: &str,
+    cronjob: &str,
+) -> Result<String> {
+    let cron: CronJob = fetch_with_ready_list(
+        opts,
+        namespace,
+        "cronjob",
+        &["get", "cronjob", cronjob, "-n", namespace, "-o", "json"],
+        &format!("get cronjob {}", cronjob),
+    )
+    .await?;
+    let jobs: OwnedJobList = run_kubectl_json(
+        opts,
+        &["get", "jobs", "-n", namespace, "-o", "json"],
+        "get jobs",
     )
     .await?;
+    let job = match latest_active_job(jobs.items, &cron.metadata.uid) {
+        Some(job) => job,
+        None => bail!(
+            "cronjob `{}` has no active jobs in namespace {}",
+            cronjob,
+            namespace
+        ),
+    };
+    choose_pod_for_job(opts, namespace, &job).await
+}
+
+fn latest_active_job(jobs: Vec<OwnedJobItem>, owner_uid: &str) -> Option<String> {
+    let mut owned: Vec<OwnedJobItem> = jobs
+        .into_iter()
+        .filter(|j| {
+            j.metadata
+                .owner_references
+                .iter()
+                .any(|o| o.uid == owner_uid)
+        })
+        .filter(|j| {
+            j.status
+                .as_ref()
+                .map(|s| s.active.unwrap_or(0) > 0 || s.ready.unwrap_or(0) > 0)
+                .unwrap_or(false)
+        })
+        .collect();
+    // RFC 3339 timestamps from the API server sort chronologically as strings.
+    owned.sort_by(|a, b| {
+        a.metadata
+            .creation_timestamp
+            .cmp(&b.metadata.creation_timestamp)
+    });
+    owned.pop().map(|j| j.metadata.name)
+}
+
+async fn select_pod(
+    opts: &KubectlOptions,
+    namespace: &str,
+    selector: &str,
+    kind: &str,
+) -> Result<String> {
+    let pods = fetch_pods(opts, namespace, selector, None).await?;
     if pods.items.is_empty() {
         bail!(
             "no pods found for {} selector `{}` in namespace {}",
@@ -364,13 +1325,7 @@ async fn select_pod(
             namespace
         );
     }
-    if let Some(p) = pods
-        .items
-        .iter()
-        .find(|p| is_ready(p))
-        .or_else(|| pods.items.iter().find(|p| is_running(p)))
-        .or_else(|| pods.items.first())
-    {
+    if let Some(p) = pick_pod(&pods.items) {
         return Ok(p.metadata.name.clone());
     }
     bail!(
@@ -381,6 +1336,37 @@ async fn select_pod(
     );
 }
 
+fn node_field_selector(node: &str) -> String {
+    format!("spec.nodeName={}", node)
+}
+
+async fn fetch_pods(
+    opts: &KubectlOptions,
+    namespace: &str,
+    selector: &str,
+    field_selector: Option<&str>,
+) -> Result<PodList> {
+    let args = pod_list_args(namespace, selector, field_selector);
+    run_kubectl_json(opts, &args, "get pods").await
+}
+
+fn pod_list_args<'a>(
+    namespace: &'a str,
+    selector: &'a str,
+    field_selector: Option<&'a str>,
+) -> Vec<&'a str> {
+    let mut args = vec!["get", "pods", "-n", namespace, "-l", selector];
+    if let Some(fields) = field_selector {
+        args.extend(["--field-selector", fields]);
+    }
+    args.extend(["-o", "json"]);
+    args
+}
+
+fn pick_pod(pods: &[PodListItem]) -> Option<&PodListItem> {
+    selector::current().select(pods).map(|i| &pods[i])
+}
+
 fn to_selector(sel: &LabelSelector) -> Result<String> {
     let mut parts = Vec::new();
     for (k, v) in &sel.match_labels {
@@ -415,17 +1401,15 @@ fn to_selector(sel: &LabelSelector) -> Result<String> {
     Ok(parts.join(","))
 }
 
-fn is_ready(pod: &PodListItem) -> bool {
-    if pod
-        .status
-        .as_ref()
+fn status_ready(status: Option<&PodStatus>) -> bool {
+    if status
         .and_then(|s| s.phase.as_ref())
         .map(|p| p == "Running")
         != Some(true)
     {
         return false;
     }
-    if let Some(conds) = pod.status.as_ref().and_then(|s| s.conditions.as_ref()) {
+    if let Some(conds) = status.and_then(|s| s.conditions.as_ref()) {
         return conds
             .iter()
             .any(|c| c.type_name == "Ready" && c.status == "True");
@@ -433,18 +1417,11 @@ fn is_ready(pod: &PodListItem) -> bool {
     false
 }
 
-fn is_running(pod: &PodListItem) -> bool {
-    pod.status
-        .as_ref()
-        .and_then(|s| s.phase.as_ref())
-        .map(|p| p == "Running")
-        .unwrap_or(false)
-}
-
 async fn list_from_json<T, F>(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     resource: &str,
+    field_selector: Option<&str>,
     mapper: F,
 ) -> Result<Vec<String>>
 where
@@ -452,49 +1429,104 @@ where
     F: FnOnce(T) -> Vec<String>,
 {
     let action = format!("get {}", resource);
-    let list: T = run_kubectl_json(
-        context,
-        &["get", resource, "-n", namespace, "-o", "json"],
-        &action,
-    )
-    .await?;
+    let mut args = vec!["get", resource, "-n", namespace];
+    if let Some(fields) = field_selector {
+        args.extend(["--field-selector", fields]);
+    }
+    args.extend(["-o", "json"]);
+    let list: T = run_kubectl_json(opts, &args, &action).await?;
     Ok(mapper(list))
 }
 
-async fn list_resources(context: Option<&str>, namespace: &str, kind: &str) -> Result<Vec<String>> {
+pub async fn list_resources(
+    opts: &KubectlOptions,
+    namespace: &str,
+    kind: &str,
+    field_selector: Option<&str>,
+) -> Result<Vec<String>> {
     match kind {
         "pod" => {
-            list_from_json(context, namespace, "pods", |pods: PodList| {
+            list_from_json(opts, namespace, "pods", field_selector, |pods: PodList| {
                 pods.items
                     .into_iter()
-                    .filter(is_ready)
+                    .filter(|pod| pod.is_ready())
                     .map(|p| p.metadata.name)
                     .collect()
             })
             .await
         }
         "deployment" => {
-            list_from_json(context, namespace, "deployments", |list: DeploymentList| {
-                list.items
-                    .into_iter()
-                    .filter(|d| {
-                        if let Some(status) = &d.status {
-                            status
-                                .available_replicas
+            list_from_json(
+                opts,
+                namespace,
+                "deployments",
+                field_selector,
+                |list: DeploymentList| {
+                    list.items
+                        .into_iter()
+                        .filter(|d| {
+                            if let Some(status) = &d.status {
+                                status
+                                    .available_replicas
+                                    .unwrap_or(0)
+                                    .saturating_add(status.ready_replicas.unwrap_or(0))
+                                    > 0
+                            } else {
+                                false
+                            }
+                        })
+                        .map(|d| d.metadata.name)
+                        .collect()
+                },
+            )
+            .await
+        }
+        "statefulset" => {
+            list_from_json(
+                opts,
+                namespace,
+                "statefulsets",
+                field_selector,
+                |list: StatefulSetList| {
+                    list.items
+                        .into_iter()
+                        .filter(|s| {
+                            s.status
+                                .as_ref()
+                                .and_then(|status| status.ready_replicas)
                                 .unwrap_or(0)
-                                .saturating_add(status.ready_replicas.unwrap_or(0))
                                 > 0
-                        } else {
-                            false
-                        }
-                    })
-                    .map(|d| d.metadata.name)
-                    .collect()
-            })
+                        })
+                        .map(|s| s.metadata.name)
+                        .collect()
+                },
+            )
+            .await
+        }
+        "daemonset" => {
+            list_from_json(
+                opts,
+                namespace,
+                "daemonsets",
+                field_selector,
+                |list: DaemonSetList| {
+                    list.items
+                        .into_iter()
+                        .filter(|d| {
+                            d.status
+                                .as_ref()
+                                .and_then(|status| status.number_ready)
+                                .unwrap_or(0)
+                                > 0
+                        })
+                        .map(|d| d.metadata.name)
+                        .collect()
+                },
+            )
             .await
         }
         "job" => {
-            list_from_json(context, namespace, "jobs", |list: JobList| {
+            list_from_json(opts, namespace, "jobs", field_selector, |list: JobList| {
                 list.items
                     .into_iter()
                     .filter(|j| {
@@ -515,14 +1547,44 @@ async fn list_resources(context: Option<&str>, namespace: &str, kind: &str) -> R
     }
 }
 
+pub async fn get_pod_logs(
+    opts: &KubectlOptions,
+    namespace: &str,
+    pod: &str,
+    container: &str,
+    tail_lines: u32,
+) -> Result<String> {
+    let output = opts
+        .command()
+        .args([
+            "logs",
+            &format!("--tail={}", tail_lines),
+            "-n",
+            namespace,
+            pod,
+            "-c",
+            container,
+        ])
+        .output()
+        .await
+        .context("failed to run kubectl logs")?;
+    if !output.status.success() {
+        bail!(
+            "kubectl logs failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 fn build_exec_command(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     pod: &str,
     container: &str,
     wants_stdin: bool,
 ) -> Command {
-    let mut cmd = kubectl_base(context);
+    let mut cmd = opts.command();
     cmd.arg("exec");
     if wants_stdin {
         cmd.arg("-i");
@@ -532,25 +1594,25 @@ fn build_exec_command(
 }
 
 pub async fn exec_capture(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     pod: &str,
     container: &str,
     command: &[&str],
 ) -> Result<String> {
-    let output = exec(context, namespace, pod, container, command, None).await?;
+    let output = exec(opts, namespace, pod, container, command, None).await?;
     if !output.status.success() {
-        bail!(
-            "kubectl exec failed: {}",
-            String::from_utf8_lossy(&output.stderr).trim()
-        );
+        let mut args = vec!["exec", "-n", namespace, pod, "-c", container, "--"];
+        args.extend_from_slice(command);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SshpodError::kubectl_failed(&args, &stderr).into());
     }
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 pub async fn exec_capture_target(target: &RemoteTarget, command: &[&str]) -> Result<String> {
     exec_capture(
-        target.context.as_deref(),
+        &target.opts,
         target.namespace.as_str(),
         target.pod.as_str(),
         target.container.as_str(),
@@ -560,13 +1622,13 @@ pub async fn exec_capture_target(target: &RemoteTarget, command: &[&str]) -> Res
 }
 
 pub async fn exec_capture_optional(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     pod: &str,
     container: &str,
     command: &[&str],
 ) -> Result<Option<String>> {
-    let output = exec(context, namespace, pod, container, command, None).await?;
+    let output = exec(opts, namespace, pod, container, command, None).await?;
     if !output.status.success() {
         return Ok(None);
     }
@@ -580,7 +1642,7 @@ pub async fn exec_capture_optional_target(
     command: &[&str],
 ) -> Result<Option<String>> {
     exec_capture_optional(
-        target.context.as_deref(),
+        &target.opts,
         target.namespace.as_str(),
         target.pod.as_str(),
         target.container.as_str(),
@@ -590,55 +1652,118 @@ pub async fn exec_capture_optional_target(
 }
 
 pub async fn exec_with_input(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     pod: &str,
     container: &str,
     command: &[&str],
-    input: &[u8],
+    mut input: impl AsyncRead + Unpin + Send,
 ) -> Result<String> {
-    let mut cmd = build_exec_command(context, namespace, pod, container, true);
+    let mut stdout = Vec::new();
+    let status = exec_stream(
+        opts,
+        namespace,
+        pod,
+        container,
+        command,
+        Some(&mut input),
+        &mut stdout,
+    )
+    .await?;
+    if !status.success() {
+        bail!("kubectl exec failed");
+    }
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn exec_stream(
+    opts: &KubectlOptions,
+    namespace: &str,
+    pod: &str,
+    container: &str,
+    command: &[&str],
+    input: Option<&mut (dyn AsyncRead + Unpin + Send)>,
+    sink: &mut (dyn AsyncWrite + Unpin + Send),
+) -> Result<ExitStatus> {
+    let mut cmd = build_exec_command(opts, namespace, pod, container, input.is_some());
     cmd.args(command);
+    stream_child(cmd, input, sink).await
+}
+
+// Runs `cmd` with its stdout copied to `sink` and `input`, if any, fed to its
+// stdin. Returns once the child has exited and its output is drained, even if
+// `input` still has data or never ends: a command need not read all of it.
+async fn stream_child(
+    mut cmd: Command,
+    input: Option<&mut (dyn AsyncRead + Unpin + Send)>,
+    sink: &mut (dyn AsyncWrite + Unpin + Send),
+) -> Result<ExitStatus> {
+    cmd.kill_on_drop(true);
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::inherit());
-    cmd.stdin(Stdio::piped());
+    cmd.stdin(if input.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    });
 
     let mut child = cmd.spawn().context("failed to spawn kubectl exec")?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .context("failed to capture kubectl exec stdout")?;
+    let stdin = child.stdin.take();
 
-    let mut input_err = None;
-    if let Some(mut stdin) = child.stdin.take() {
-        if let Err(err) = stdin.write_all(input).await {
-            input_err = Some(err);
+    let feed = async move {
+        if let (Some(mut stdin), Some(reader)) = (stdin, input) {
+            tokio::io::copy(reader, &mut stdin).await?;
+            stdin.shutdown().await?;
         }
-    }
-
-    let output = child
-        .wait_with_output()
-        .await
-        .context("failed to wait for kubectl exec")?;
-
-    if !output.status.success() {
-        if let Some(err) = input_err {
-            bail!("kubectl exec failed (stdin error: {})", err);
-        } else {
-            bail!("kubectl exec failed");
+        Ok::<_, std::io::Error>(())
+    };
+    let finish = async {
+        tokio::io::copy(&mut stdout, sink)
+            .await
+            .context("failed to stream kubectl exec output")?;
+        sink.flush()
+            .await
+            .context("failed to stream kubectl exec output")?;
+        child
+            .wait()
+            .await
+            .context("failed to wait for kubectl exec")
+    };
+    tokio::pin!(feed, finish);
+    let mut fed = false;
+    let mut input_result = Ok(());
+    let status = loop {
+        tokio::select! {
+            result = &mut feed, if !fed => {
+                fed = true;
+                input_result = result;
+            }
+            status = &mut finish => break status?,
         }
+    };
+    match input_result {
+        // The command stopped reading before the input ended.
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(err) if status.success() => {
+            return Err(err).context("kubectl exec stdin error");
+        }
+        _ => {}
     }
-
-    if let Some(err) = input_err {
-        bail!("kubectl exec stdin error: {}", err);
-    }
-
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    Ok(status)
 }
 
 pub async fn exec_with_input_target(
     target: &RemoteTarget,
     command: &[&str],
-    input: &[u8],
+    input: impl AsyncRead + Unpin + Send,
 ) -> Result<String> {
     exec_with_input(
-        target.context.as_deref(),
+        &target.opts,
         target.namespace.as_str(),
         target.pod.as_str(),
         target.container.as_str(),
@@ -648,16 +1773,79 @@ pub async fn exec_with_input_target(
     .await
 }
 
+pub async fn copy_to_pod(
+    opts: &KubectlOptions,
+    namespace: &str,
+    pod: &str,
+    container: &str,
+    local_path: &Path,
+    remote_path: &str,
+) -> Result<()> {
+    let remote = format!("{}/{}:{}", namespace, pod, remote_path);
+    run_cp(opts, container, local_path.as_os_str(), remote.as_ref()).await
+}
+
+pub async fn copy_from_pod(
+    opts: &KubectlOptions,
+    namespace: &str,
+    pod: &str,
+    container: &str,
+    remote_path: &str,
+    local_path: &Path,
+) -> Result<()> {
+    let remote = format!("{}/{}:{}", namespace, pod, remote_path);
+    run_cp(opts, container, remote.as_ref(), local_path.as_os_str()).await
+}
+
+async fn run_cp(opts: &KubectlOptions, container: &str, src: &OsStr, dst: &OsStr) -> Result<()> {
+    let mut cmd = opts.command();
+    cmd.args(["cp", "-c", container]);
+    // `kubectl cp --retries` resumes interrupted transfers; it appeared in 1.23.
+    let version = client_version(opts).await.unwrap_or_default();
+    if supports_cp_retries(&version) {
+        cmd.arg(format!("--retries={}", opts.retries.max(CP_RETRIES)));
+    }
+    cmd.arg(src).arg(dst);
+    let output = cmd
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("failed to run kubectl cp")?;
+    if !output.status.success() {
+        bail!(
+            "kubectl cp {} {} failed (the container needs `tar`): {}",
+            src.to_string_lossy(),
+            dst.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn supports_cp_retries(git_version: &str) -> bool {
+    let mut parts = git_version.trim_start_matches('v').split('.');
+    let major = parts.next().and_then(|p| p.parse::<u32>().ok());
+    let minor = parts.next().and_then(|p| {
+        let digits: String = p.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse::<u32>().ok()
+    });
+    match (major, minor) {
+        (Some(major), Some(minor)) => (major, minor) >= (1, 23),
+        _ => false,
+    }
+}
+
 async fn exec(
-    context: Option<&str>,
+    opts: &KubectlOptions,
     namespace: &str,
     pod: &str,
     container: &str,
     command: &[&str],
     input: Option<&[u8]>,
 ) -> Result<Output> {
-    let mut cmd = build_exec_command(context, namespace, pod, container, input.is_some());
+    let mut cmd = build_exec_command(opts, namespace, pod, container, input.is_some());
     cmd.args(command);
+    cmd.kill_on_drop(true);
     cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::piped());
     if input.is_some() {
@@ -685,10 +1873,117 @@ async fn exec(
 mod tests {
     use super::*;
 
+    #[test]
+    fn locate_reports_missing_binary_path() {
+        let missing = KubectlBinary::Path(PathBuf::from("/nonexistent/sshpod/kubectl"));
+        let err = locate(&missing).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "kubectl binary /nonexistent/sshpod/kubectl not found"
+        );
+    }
+
+    #[test]
+    fn detects_oc_when_kubectl_is_missing() {
+        let base = std::env::temp_dir().join(format!("sshpod-bin-{}", std::process::id()));
+        let (kube_dir, oc_dir) = (base.join("kube"), base.join("oc"));
+        std::fs::create_dir_all(&kube_dir).unwrap();
+        std::fs::create_dir_all(&oc_dir).unwrap();
+        let exe = |name: &str| format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+        std::fs::write(kube_dir.join(exe("kubectl")), "").unwrap();
+        std::fs::write(oc_dir.join(exe("oc")), "").unwrap();
+        let path = |dirs: &[&PathBuf]| Some(std::env::join_paths(dirs).unwrap());
+
+        assert_eq!(detect_binary(path(&[&oc_dir])), KubectlBinary::Oc);
+        assert_eq!(
+            detect_binary(path(&[&oc_dir, &kube_dir])),
+            KubectlBinary::Kubectl
+        );
+        assert_eq!(detect_binary(path(&[&base])), KubectlBinary::Kubectl);
+        assert_eq!(detect_binary(None), KubectlBinary::Kubectl);
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn parses_pods_across_namespaces() {
+        let json = br#"{"items":[
+            {"metadata":{"name":"api-0","uid":"u1","namespace":"prod"},
+             "spec":{"containers":[{"name":"api"}]},
+             "status":{"phase":"Running","conditions":[{"type":"Ready","status":"True"}]}},
+            {"metadata":{"name":"batch-x","uid":"u2","namespace":"etl"},
+             "spec":{"containers":[{"name":"main"}],"initContainers":[{"name":"setup"}]},
+             "status":{"phase":"Pending"}}
+        ]}"#;
+        let list: AllPodList = serde_json::from_slice(json).unwrap();
+        assert!(status_ready(list.items[0].status.as_ref()));
+        assert!(!status_ready(list.items[1].status.as_ref()));
+        let (namespace, info) = list.items.into_iter().nth(1).unwrap().into_info();
+        assert_eq!(namespace, "etl");
+        assert_eq!(info.init_containers, ["setup"]);
+    }
+
+    #[test]
+    fn reads_every_context_namespace() {
+        let json = br#"{"contexts":[
+            {"name":"kind-dev","context":{"cluster":"kind-dev","namespace":"team-a"}},
+            {"name":"prod","context":{"cluster":"prod","user":"me"}},
+            {"name":"blank","context":{"namespace":""}}
+        ]}"#;
+        let namespaces = parse_context_namespaces(json).unwrap();
+        assert_eq!(namespaces["kind-dev"].as_deref(), Some("team-a"));
+        assert_eq!(namespaces["prod"], None);
+        assert_eq!(namespaces["blank"], None);
+        assert!(parse_context_namespaces(b"{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn cp_retries_need_kubectl_1_23() {
+        assert!(supports_cp_retries("v1.23.0"));
+        assert!(supports_cp_retries("v1.30.2-gke.1100"));
+        assert!(supports_cp_retries("v2.0.0"));
+        assert!(!supports_cp_retries("v1.22.17"));
+        assert!(!supports_cp_retries(""));
+    }
+
+    #[test]
+    fn service_selector_from_map() {
+        let spec: ServiceSpec =
+            serde_json::from_str(r#"{"selector":{"tier":"api","app":"web"}}"#).unwrap();
+        assert_eq!(service_selector(&spec), "app=web,tier=api");
+        let headless: ServiceSpec = serde_json::from_str(r#"{"clusterIP":"None"}"#).unwrap();
+        assert_eq!(service_selector(&headless), "");
+    }
+
+    #[test]
+    fn builder_requires_namespace_pod_and_container() {
+        let target = RemoteTarget::builder()
+            .context(Some("prod".into()))
+            .namespace("web".into())
+            .pod("web-0".into())
+            .container("app".into())
+            .build()
+            .unwrap();
+        assert_eq!(target.opts.context.as_deref(), Some("prod"));
+        assert_eq!(
+            (target.namespace.as_str(), target.pod.as_str()),
+            ("web", "web-0")
+        );
+        assert!(!target.init_container);
+        let missing = RemoteTarget::builder()
+            .namespace("web".into())
+            .pod("web-0".into())
+            .build()
+            .unwrap_err();
+        assert!(missing.to_string().contains("container"));
+    }
+
     #[test]
     fn test_is_ready_true() {
         let pod = PodListItem {
-            metadata: PodMetadataName { name: "p".into() },
+            metadata: PodMetadataName {
+                name: "p".into(),
+                creation_timestamp: None,
+            },
             status: Some(PodStatus {
                 phase: Some("Running".into()),
                 conditions: Some(vec![PodCondition {
@@ -697,18 +1992,196 @@ mod tests {
                 }]),
             }),
         };
-        assert!(is_ready(&pod));
+        assert!(pod.is_ready());
+    }
+
+    #[test]
+    fn deployment_status_describes_ready_replicas() {
+        let status: DeploymentStatus =
+            serde_json::from_str(r#"{"replicas": 3, "availableReplicas": 1}"#).unwrap();
+        assert_eq!(
+            status.describe("web"),
+            "deployment `web` has 0/3 ready replicas"
+        );
+    }
+
+    #[test]
+    fn job_status_describes_completed_job() {
+        let status: JobStatus = serde_json::from_str(r#"{"succeeded": 1}"#).unwrap();
+        assert_eq!(
+            status.describe("data-pipeline"),
+            "job `data-pipeline` has completed (succeeded: 1, active: 0), no running pods available for SSH"
+        );
+    }
+
+    #[test]
+    fn test_latest_active_job_picks_newest_owned() {
+        let job = |name: &str, ts: &str, owner: &str, active: u32| OwnedJobItem {
+            metadata: OwnedJobMetadata {
+                name: name.into(),
+                creation_timestamp: Some(ts.into()),
+                owner_references: vec![OwnerReference { uid: owner.into() }],
+            },
+            status: Some(JobStatus {
+                succeeded: None,
+                active: Some(active),
+                ..Default::default()
+            }),
+        };
+        let jobs = vec![
+            job("old", "2024-01-01T00:00:00Z", "cron", 1),
+            job("new", "2024-01-02T00:00:00Z", "cron", 1),
+            job("done", "2024-01-03T00:00:00Z", "cron", 0),
+            job("other", "2024-01-04T00:00:00Z", "someone-else", 1),
+        ];
+        assert_eq!(latest_active_job(jobs, "cron").as_deref(), Some("new"));
+        assert_eq!(latest_active_job(Vec::new(), "cron"), None);
+    }
+
+    #[test]
+    fn test_sort_by_ordinal_is_numeric() {
+        let mut pods: Vec<PodListItem> = ["db-10", "db-9", "db-0", "db-x", "db-2"]
+            .into_iter()
+            .map(|name| PodListItem {
+                metadata: PodMetadataName {
+                    name: name.into(),
+                    creation_timestamp: None,
+                },
+                status: None,
+            })
+            .collect();
+        sort_by_ordinal(&mut pods);
+        let names: Vec<&str> = pods.iter().map(|p| p.metadata.name.as_str()).collect();
+        assert_eq!(names, ["db-0", "db-2", "db-9", "db-10", "db-x"]);
+    }
+
+    #[test]
+    fn test_pod_list_json_maps_to_pod_info() {
+        let json = r#"{"items":[
+            {"metadata":{"name":"a","uid":"u1"},"spec":{"containers":[{"name":"main"},{"name":"side"}],
+                "initContainers":[{"name":"setup"}]}},
+            {"metadata":{"name":"b","uid":"u2"},"spec":{"containers":[{"name":"main"}]}}
+        ]}"#;
+        let list: PodInfoList = serde_json::from_str(json).unwrap();
+        let infos: Vec<PodInfo> = list.items.into_iter().map(pod_info_from).collect();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].name, "a");
+        assert_eq!(infos[0].uid, "u1");
+        assert_eq!(infos[0].containers, ["main", "side"]);
+        assert_eq!(infos[0].init_containers, ["setup"]);
+        assert_eq!(infos[1].containers, ["main"]);
+        assert!(infos[1].init_containers.is_empty());
+
+        let ready: PodList = serde_json::from_str(
+            r#"{"items":[{"metadata":{"name":"a"},"spec":{"nodeName":"n1"},
+                "status":{"phase":"Running","conditions":[{"type":"Ready","status":"True"}]}}]}"#,
+        )
+        .unwrap();
+        assert!(ready.items[0].is_ready());
+    }
+
+    #[test]
+    fn test_namespace_list_json() {
+        let list: NamespaceList = serde_json::from_str(
+            r#"{"items":[{"metadata":{"name":"default"}},{"metadata":{"name":"kube-system"}}]}"#,
+        )
+        .unwrap();
+        let names: Vec<String> = list.items.into_iter().map(|n| n.metadata.name).collect();
+        assert_eq!(names, ["default", "kube-system"]);
+    }
+
+    #[tokio::test]
+    async fn stream_child_returns_when_child_ignores_stdin() {
+        // More input than a pipe buffers, for a child that reads none of it.
+        let mut input: &[u8] = &[b'y'; 2_000_000];
+        let mut out = Vec::new();
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo done"]);
+        let status = stream_child(cmd, Some(&mut input), &mut out).await.unwrap();
+        assert!(status.success());
+        assert_eq!(out, b"done\n");
+    }
+
+    #[tokio::test]
+    async fn stream_child_returns_while_stdin_stays_open() {
+        // Like `exec --stdin` at a terminal: input that never reaches EOF.
+        let (_writer, mut input) = tokio::io::duplex(64);
+        let mut out = Vec::new();
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "exit 3"]);
+        let status = tokio::time::timeout(
+            Duration::from_secs(5),
+            stream_child(cmd, Some(&mut input), &mut out),
+        )
+        .await
+        .expect("exec must return once the command exits")
+        .unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_pod_list_args_filter_by_node() {
+        let on_node = node_field_selector("worker-1");
+        let args = pod_list_args("logging", "app=fluentd", Some(&on_node));
+        assert_eq!(
+            args,
+            [
+                "get",
+                "pods",
+                "-n",
+                "logging",
+                "-l",
+                "app=fluentd",
+                "--field-selector",
+                "spec.nodeName=worker-1",
+                "-o",
+                "json"
+            ]
+        );
+        let args = pod_list_args("logging", "app=fluentd", None);
+        assert!(!args.contains(&"--field-selector"));
+        assert_eq!(args.last(), Some(&"json"));
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_and_caps() {
+        assert_eq!(retry_delay(1), Duration::from_millis(500));
+        assert_eq!(retry_delay(2), Duration::from_millis(1000));
+        assert_eq!(retry_delay(3), Duration::from_millis(2000));
+        assert_eq!(retry_delay(5), Duration::from_millis(8000));
+        assert_eq!(retry_delay(6), Duration::from_secs(10));
+        assert_eq!(retry_delay(40), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_is_transient_failure() {
+        assert!(is_transient_failure(
+            "Unable to connect to the server: dial tcp 10.0.0.1:443: i/o timeout"
+        ));
+        assert!(is_transient_failure(
+            "Error from server (TooManyRequests): the server has received too many requests"
+        ));
+        assert!(is_transient_failure("the server responded with HTTP 503"));
+        assert!(!is_transient_failure(
+            "Error from server (NotFound): pods \"x\" not found"
+        ));
+        assert!(!is_transient_failure(
+            "error: You must be logged in to the server (Unauthorized)"
+        ));
     }
 
     #[test]
     fn test_is_ready_false_when_not_running() {
         let pod = PodListItem {
-            metadata: PodMetadataName { name: "p".into() },
+            metadata: PodMetadataName {
+                name: "p".into(),
+                creation_timestamp: None,
+            },
             status: Some(PodStatus {
                 phase: Some("Pending".into()),
                 conditions: None,
             }),
         };
-        assert!(!is_ready(&pod));
+        assert!(!pod.is_ready());
     }
 }
@@ -0,0 +1,69 @@
+//! sshpod as a library: the pieces behind the `sshpod` binary, for tools that
+//! want to reach Kubernetes Pods over SSH without shelling out to it.
+//!
+//! Parse a hostspec and look at what it targets:
+//!
+//! ```
+//! use sshpod::{parse, Target};
+//!
+//! let spec = parse("container--app.deployment--web.namespace--prod.sshpod")?;
+//! assert_eq!(spec.target, Target::Deployment("web".to_string()));
+//! assert_eq!(spec.namespace.as_deref(), Some("prod"));
+//! assert_eq!(spec.container.as_deref(), Some("app"));
+//! # Ok::<(), sshpod::HostSpecError>(())
+//! ```
+//!
+//! Install the sshd bundle into a container you already know:
+//!
+//! ```no_run
+//! use sshpod::{detect_remote_arch, ensure_bundle, RemoteTarget};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let target = RemoteTarget::builder()
+//!     .context(Some("staging".to_string()))
+//!     .namespace("prod".to_string())
+//!     .pod("web-7d9f8c6b5-x2k4q".to_string())
+//!     .container("app".to_string())
+//!     .build()?;
+//! let arch = detect_remote_arch(&target).await?;
+//! ensure_bundle(&target, "/tmp/sshpod/example/app", &arch).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Errors are [`anyhow::Error`]s; the ones worth matching on carry a
+//! [`SshpodError`] that can be recovered with `downcast_ref`.
+#![deny(missing_docs)]
+
+mod bundle;
+mod check;
+pub mod cli;
+mod config;
+mod copy;
+mod diagnose;
+mod embedded;
+mod error;
+mod exec;
+mod hostspec;
+mod install;
+mod keys;
+mod kube_api;
+mod kubectl;
+mod list;
+mod paths;
+mod port_forward;
+mod proxy;
+mod proxy_io;
+mod remote;
+mod resolve;
+mod selector;
+
+pub use bundle::{detect_remote_arch, ensure_bundle};
+pub use error::SshpodError;
+pub use hostspec::{parse, HostSpec, HostSpecError, Target};
+pub use kubectl::{
+    list_namespaces, list_pods_in_namespace, KubectlOptions, PodInfo, RemoteTarget,
+    RemoteTargetBuilder,
+};
+pub use proxy::run as proxy_run;
+pub use resolve::resolve_remote_target;
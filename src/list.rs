@@ -0,0 +1,199 @@
+use crate::cli::{ListArgs, OutputFormat};
+use crate::hostspec::{HostSpec, HostSpecPartial, Target};
+use crate::kubectl::{self, KubectlOptions};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+const KINDS: &[(&str, &str, &str)] = &[
+    ("pod", "Pod", "Ready"),
+    ("deployment", "Deployment", "Available"),
+    ("statefulset", "StatefulSet", "Ready"),
+    ("daemonset", "DaemonSet", "Ready"),
+    ("job", "Job", "Active"),
+];
+
+#[derive(Serialize)]
+struct Entry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    kind: &'static str,
+    name: String,
+    status: &'static str,
+}
+
+pub async fn run(args: ListArgs) -> Result<()> {
+    let opts = KubectlOptions {
+        context: args.context.clone(),
+        kubeconfig: args.kubeconfig.clone(),
+        ..Default::default()
+    };
+    if let Some(ctx) = &args.context {
+        kubectl::ensure_context_exists(ctx, &opts).await?;
+    }
+
+    let mut entries = Vec::new();
+    if args.all_namespaces {
+        let pods = kubectl::list_all_pods(&opts, false)
+            .await
+            .context("failed to list pods")?;
+        entries.extend(pods.into_iter().map(|(namespace, info)| Entry {
+            namespace: Some(namespace),
+            kind: "Pod",
+            name: info.name,
+            status: "Ready",
+        }));
+    } else {
+        let namespace =
+            kubectl::resolve_namespace(&opts, args.namespace.as_deref(), args.context.as_deref())
+                .await?;
+        for (kind, label, status) in KINDS {
+            // Field names differ per kind; the selector is meant for pods.
+            let field_selector = match (*kind, &args.field_selector) {
+                ("pod", Some(fields)) => Some(fields.as_str()),
+                (_, Some(_)) => continue,
+                (_, None) => None,
+            };
+            let names = kubectl::list_resources(&opts, &namespace, kind, field_selector)
+                .await
+                .with_context(|| format!("failed to list {}s", kind))?;
+            entries.extend(names.into_iter().map(|name| Entry {
+                namespace: None,
+                kind: label,
+                name,
+                status,
+            }));
+        }
+    }
+
+    match args.output {
+        OutputFormat::Table => print!("{}", render_table(&entries)),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+    }
+    Ok(())
+}
+
+// Hostnames for shell completion of `--host`. Segments the user has already
+// typed pick the context, namespace, container and target kind.
+pub async fn hostnames(typed: &HostSpecPartial) -> Result<Vec<String>> {
+    let opts = KubectlOptions {
+        context: typed.context.clone(),
+        ..Default::default()
+    };
+    let namespace =
+        kubectl::resolve_namespace(&opts, typed.namespace.as_deref(), typed.context.as_deref())
+            .await?;
+    let kinds: Vec<&str> = match &typed.target {
+        Some(target) => target_kind(target).into_iter().collect(),
+        None => KINDS.iter().map(|(kind, _, _)| *kind).collect(),
+    };
+    let mut hosts = Vec::new();
+    for kind in kinds {
+        for name in kubectl::list_resources(&opts, &namespace, kind, None).await? {
+            let target = match kind {
+                "pod" => Target::Pod(name),
+                "deployment" => Target::Deployment(name),
+                "statefulset" => Target::StatefulSet(name),
+                "daemonset" => Target::DaemonSet(name),
+                _ => Target::Job(name),
+            };
+            let spec = HostSpec {
+                context: typed.context.clone(),
+                namespace: (!namespace.is_empty()).then(|| namespace.clone()),
+                target,
+                container: typed.container.clone(),
+                init_container: false,
+                node: None,
+            };
+            hosts.push(spec.to_hostname());
+        }
+    }
+    Ok(hosts)
+}
+
+fn target_kind(target: &Target) -> Option<&'static str> {
+    match target {
+        Target::Pod(_) => Some("pod"),
+        Target::Deployment(_) => Some("deployment"),
+        Target::StatefulSet(_) => Some("statefulset"),
+        Target::DaemonSet(_) => Some("daemonset"),
+        Target::Job(_) => Some("job"),
+        _ => None,
+    }
+}
+
+fn render_table(entries: &[Entry]) -> String {
+    let with_namespace = entries.iter().any(|e| e.namespace.is_some());
+    let ns_width = entries
+        .iter()
+        .filter_map(|e| e.namespace.as_ref().map(String::len))
+        .fold(9, usize::max);
+    let kind_width = entries.iter().map(|e| e.kind.len()).fold(4, usize::max);
+    let name_width = entries.iter().map(|e| e.name.len()).fold(4, usize::max);
+    let namespace_cell = |ns: &str| {
+        if with_namespace {
+            format!("{:ns_width$}  ", ns)
+        } else {
+            String::new()
+        }
+    };
+    let mut out = format!(
+        "{}{:kind_width$}  {:name_width$}  STATUS\n",
+        namespace_cell("NAMESPACE"),
+        "KIND",
+        "NAME"
+    );
+    for entry in entries {
+        out.push_str(&format!(
+            "{}{:kind_width$}  {:name_width$}  {}\n",
+            namespace_cell(entry.namespace.as_deref().unwrap_or("")),
+            entry.kind,
+            entry.name,
+            entry.status
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_aligned_table() {
+        let entries = vec![
+            Entry {
+                namespace: None,
+                kind: "Pod",
+                name: "api-0".into(),
+                status: "Ready",
+            },
+            Entry {
+                namespace: None,
+                kind: "Deployment",
+                name: "web".into(),
+                status: "Available",
+            },
+        ];
+        assert_eq!(
+            render_table(&entries),
+            "KIND        NAME   STATUS\n\
+             Pod         api-0  Ready\n\
+             Deployment  web    Available\n"
+        );
+    }
+
+    #[test]
+    fn adds_namespace_column_when_present() {
+        let entries = vec![Entry {
+            namespace: Some("prod".into()),
+            kind: "Pod",
+            name: "api-0".into(),
+            status: "Ready",
+        }];
+        assert_eq!(
+            render_table(&entries),
+            "NAMESPACE  KIND  NAME   STATUS\n\
+             prod       Pod   api-0  Ready\n"
+        );
+    }
+}
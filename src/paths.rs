@@ -2,8 +2,97 @@ use anyhow::{Context, Result};
 use std::path::PathBuf;
 
 pub fn home_dir() -> Result<PathBuf> {
-    std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .map(PathBuf::from)
+    #[cfg(windows)]
+    let home = std::env::var("USERPROFILE").or_else(|_| std::env::var("HOME"));
+    #[cfg(not(windows))]
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE"));
+    home.map(PathBuf::from)
         .context("failed to determine home directory; set HOME")
 }
+
+// OpenSSH for Windows also reads %USERPROFILE%\.ssh\config.
+pub fn ssh_dir() -> Result<PathBuf> {
+    Ok(home_dir()?.join(".ssh"))
+}
+
+pub fn cache_dir() -> Result<PathBuf> {
+    ensure_dir(override_or("SSHPOD_CACHE_DIR", default_cache_dir)?)
+}
+
+// Only read from, so it is not created; a read-only home must not break startup.
+pub fn config_dir() -> Result<PathBuf> {
+    override_or("SSHPOD_CONFIG_DIR", default_config_dir)
+}
+
+fn override_or(var: &str, default: fn() -> Result<PathBuf>) -> Result<PathBuf> {
+    match std::env::var_os(var).filter(|v| !v.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => default(),
+    }
+}
+
+fn ensure_dir(dir: PathBuf) -> Result<PathBuf> {
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+#[cfg(not(windows))]
+fn default_cache_dir() -> Result<PathBuf> {
+    Ok(xdg_base("XDG_CACHE_HOME", ".cache")?.join("sshpod"))
+}
+
+#[cfg(not(windows))]
+fn default_config_dir() -> Result<PathBuf> {
+    Ok(xdg_base("XDG_CONFIG_HOME", ".config")?.join("sshpod"))
+}
+
+#[cfg(windows)]
+fn default_cache_dir() -> Result<PathBuf> {
+    Ok(known_folder("LOCALAPPDATA", "AppData\\Local")?.join("sshpod"))
+}
+
+#[cfg(windows)]
+fn default_config_dir() -> Result<PathBuf> {
+    Ok(known_folder("APPDATA", "AppData\\Roaming")?.join("sshpod"))
+}
+
+// The XDG spec says relative values must be ignored.
+#[cfg(not(windows))]
+fn xdg_base(var: &str, fallback: &str) -> Result<PathBuf> {
+    match std::env::var_os(var).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Ok(dir),
+        _ => Ok(home_dir()?.join(fallback)),
+    }
+}
+
+#[cfg(windows)]
+fn known_folder(var: &str, fallback: &str) -> Result<PathBuf> {
+    match std::env::var_os(var).filter(|v| !v.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(home_dir()?.join(fallback)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Both overrides in one test so parallel tests never race on the variables.
+    #[test]
+    fn env_overrides_pick_dirs() {
+        let base = std::env::temp_dir().join(format!("sshpod-paths-{}", std::process::id()));
+        let cache = base.join("cache");
+        let config = base.join("nested").join("config");
+        std::env::set_var("SSHPOD_CACHE_DIR", &cache);
+        std::env::set_var("SSHPOD_CONFIG_DIR", &config);
+
+        assert_eq!(cache_dir().unwrap(), cache);
+        assert_eq!(config_dir().unwrap(), config);
+        assert!(cache.is_dir());
+        assert!(!config.exists());
+
+        std::env::remove_var("SSHPOD_CACHE_DIR");
+        std::env::remove_var("SSHPOD_CONFIG_DIR");
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
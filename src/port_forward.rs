@@ -1,114 +1,133 @@
+use crate::kubectl::KubectlOptions;
 use anyhow::{anyhow, Context, Result};
-use log::debug;
+use log::{debug, warn};
 use std::process::Stdio;
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use tokio::net::TcpStream;
+use tokio::sync::{watch, Mutex};
 use tokio::task::JoinHandle;
 use tokio::time::{timeout, Duration};
 
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+const HEALTH_CHECK_FAILURES: u32 = 2;
+
+type OutputTask = JoinHandle<Result<()>>;
+
+/// What kubectl port-forward connects to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardTarget {
+    Pod(String),
+    /// Kubernetes picks one of the Service's pods.
+    Service(String),
+}
+
+impl ForwardTarget {
+    fn resource(&self) -> String {
+        match self {
+            ForwardTarget::Pod(name) => format!("pod/{}", name),
+            ForwardTarget::Service(name) => format!("svc/{}", name),
+        }
+    }
+}
+
+impl std::fmt::Display for ForwardTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.resource())
+    }
+}
+
 pub struct PortForward {
-    child: tokio::process::Child,
-    stdout_task: Option<JoinHandle<Result<()>>>,
-    stderr_task: Option<JoinHandle<Result<()>>>,
+    opts: KubectlOptions,
+    namespace: String,
+    target: ForwardTarget,
+    remote_port: u16,
+    local_port: Option<u16>,
+    child: Arc<Mutex<tokio::process::Child>>,
+    probe_port: bool,
+    stdout_task: Option<OutputTask>,
+    stderr_task: Option<OutputTask>,
+    health_task: Option<JoinHandle<()>>,
+    health: watch::Sender<bool>,
 }
 
 impl PortForward {
+    /// Starts kubectl port-forward and watches it for an exit of kubectl.
+    ///
+    /// With `probe_port`, the local port is also connected to every few seconds
+    /// to catch a forward that hangs without exiting. Each probe reaches the
+    /// remote end, so a probed sshd logs a connection each time; leave it off
+    /// for long-lived sessions.
     pub async fn start(
-        context: Option<&str>,
+        opts: &KubectlOptions,
         namespace: &str,
-        pod: &str,
+        target: ForwardTarget,
         remote_port: u16,
+        local_port: Option<u16>,
+        probe_port: bool,
     ) -> Result<(PortForward, u16)> {
-        let mut cmd = Command::new("kubectl");
-        if let Some(ctx) = context {
-            cmd.arg("--context").arg(ctx);
-        }
-        cmd.args([
-            "port-forward",
-            "--address",
-            "localhost",
-            "-n",
-            namespace,
-            &format!("pod/{}", pod),
-            &format!(":{}", remote_port),
-        ]);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let mut child = cmd
-            .spawn()
-            .context("failed to spawn kubectl port-forward process")?;
-
-        let stdout = child
-            .stdout
-            .take()
-            .context("failed to capture port-forward stdout")?;
-        let stderr = child
-            .stderr
-            .take()
-            .context("failed to capture port-forward stderr")?;
-
-        let mut stdout_reader = BufReader::new(stdout).lines();
-        let mut stderr_reader = BufReader::new(stderr).lines();
-
-        let port = timeout(Duration::from_secs(10), async {
-            loop {
-                tokio::select! {
-                    line = stdout_reader.next_line() => {
-                        match line.context("failed to read port-forward stdout")? {
-                            Some(l) => {
-                                if let Some(port) = parse_port(&l) {
-                                    debug!("[port-forward] {}", l);
-                                    break Ok(port);
-                                }
-                                debug!("[port-forward] {}", l);
-                            }
-                            None => break Err(anyhow!("kubectl port-forward exited before reporting a port")),
-                        }
-                    }
-                    line = stderr_reader.next_line() => {
-                        if let Some(l) = line.context("failed to read port-forward stderr")? {
-                            debug!("[port-forward] {}", l)
-                        }
-                    }
-                    status = child.wait() => {
-                        let status = status.context("failed to wait for port-forward process")?;
-                        break Err(anyhow!("kubectl port-forward exited early with status {}", status));
-                    }
-                }
-            }
-        })
-        .await
-        .context("timed out waiting for port-forward to assign a local port")??;
-
-        let stdout_task = tokio::spawn(async move {
-            while let Some(line) = stdout_reader.next_line().await? {
-                debug!("[port-forward] {}", line);
-            }
-            Ok::<_, anyhow::Error>(())
-        });
-        let stderr_task = tokio::spawn(async move {
-            while let Some(line) = stderr_reader.next_line().await? {
-                debug!("[port-forward] {}", line);
-            }
-            Ok::<_, anyhow::Error>(())
-        });
-
+        let (child, stdout_task, stderr_task, port) =
+            spawn(opts, namespace, &target, remote_port, local_port).await?;
+        let child = Arc::new(Mutex::new(child));
+        let (health, _) = watch::channel(true);
+        let health_task = spawn_health_check(port, child.clone(), probe_port, health.clone());
         Ok((
             PortForward {
+                opts: opts.clone(),
+                namespace: namespace.to_string(),
+                target,
+                remote_port,
+                local_port,
                 child,
+                probe_port,
                 stdout_task: Some(stdout_task),
                 stderr_task: Some(stderr_task),
+                health_task: Some(health_task),
+                health,
             },
             port,
         ))
     }
 
+    pub async fn reconnect(&mut self) -> Result<u16> {
+        self.stop().await?;
+        let (child, stdout_task, stderr_task, port) = spawn(
+            &self.opts,
+            &self.namespace,
+            &self.target,
+            self.remote_port,
+            self.local_port,
+        )
+        .await?;
+        self.child = Arc::new(Mutex::new(child));
+        self.stdout_task = Some(stdout_task);
+        self.stderr_task = Some(stderr_task);
+        self.health.send_replace(true);
+        self.health_task = Some(spawn_health_check(
+            port,
+            self.child.clone(),
+            self.probe_port,
+            self.health.clone(),
+        ));
+        Ok(port)
+    }
+
+    /// Receives `false` once kubectl exits or, when probed, the local port stops accepting connections.
+    pub fn health_watch(&self) -> watch::Receiver<bool> {
+        self.health.subscribe()
+    }
+
     pub async fn stop(&mut self) -> Result<()> {
-        if self.child.id().is_some() {
-            let _ = self.child.start_kill();
+        if let Some(handle) = self.health_task.take() {
+            handle.abort();
+        }
+        let mut child = self.child.lock().await;
+        if child.id().is_some() {
+            let _ = child.start_kill();
         }
-        let _ = self.child.wait().await;
+        let _ = child.wait().await;
+        drop(child);
 
         if let Some(handle) = self.stdout_task.take() {
             let _ = handle.await;
@@ -120,6 +139,150 @@ impl PortForward {
     }
 }
 
+fn spawn_health_check(
+    port: u16,
+    child: Arc<Mutex<tokio::process::Child>>,
+    probe_port: bool,
+    health: watch::Sender<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            // A dead kubectl is certain and costs nothing to notice.
+            if let Ok(Some(status)) = child.lock().await.try_wait() {
+                warn!("[port-forward] kubectl port-forward exited ({})", status);
+                health.send_replace(false);
+                return;
+            }
+            if !probe_port {
+                continue;
+            }
+            let connected = matches!(
+                timeout(
+                    HEALTH_CHECK_TIMEOUT,
+                    TcpStream::connect(("127.0.0.1", port))
+                )
+                .await,
+                Ok(Ok(_))
+            );
+            if connected {
+                failures = 0;
+                health.send_if_modified(|healthy| !std::mem::replace(healthy, true));
+                continue;
+            }
+            failures += 1;
+            debug!(
+                "[port-forward] health check on port {} failed ({})",
+                port, failures
+            );
+            if failures >= HEALTH_CHECK_FAILURES
+                && health.send_if_modified(|healthy| std::mem::replace(healthy, false))
+            {
+                warn!(
+                    "[port-forward] localhost:{} stopped accepting connections",
+                    port
+                );
+            }
+        }
+    })
+}
+
+async fn spawn(
+    opts: &KubectlOptions,
+    namespace: &str,
+    target: &ForwardTarget,
+    remote_port: u16,
+    local_port: Option<u16>,
+) -> Result<(tokio::process::Child, OutputTask, OutputTask, u16)> {
+    let ports = match local_port {
+        Some(local) => format!("{}:{}", local, remote_port),
+        None => format!(":{}", remote_port),
+    };
+    let mut cmd = opts.command();
+    cmd.args([
+        "port-forward",
+        "--address",
+        "localhost",
+        "-n",
+        namespace,
+        &target.resource(),
+        &ports,
+    ]);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .context("failed to spawn kubectl port-forward process")?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to capture port-forward stdout")?;
+    let stderr = child
+        .stderr
+        .take()
+        .context("failed to capture port-forward stderr")?;
+
+    let mut stdout_reader = BufReader::new(stdout).lines();
+    let mut stderr_reader = BufReader::new(stderr).lines();
+
+    // kubectl reports failures such as an already-bound local port on stderr.
+    let mut last_error: Option<String> = None;
+    let port = timeout(Duration::from_secs(10), async {
+        loop {
+            tokio::select! {
+                line = stdout_reader.next_line() => {
+                    match line.context("failed to read port-forward stdout")? {
+                        Some(l) => {
+                            if let Some(port) = parse_port(&l) {
+                                debug!("[port-forward] {}", l);
+                                break Ok(port);
+                            }
+                            debug!("[port-forward] {}", l);
+                        }
+                        None => break Err(match &last_error {
+                            Some(err) => anyhow!("kubectl port-forward exited before reporting a port: {}", err),
+                            None => anyhow!("kubectl port-forward exited before reporting a port"),
+                        }),
+                    }
+                }
+                line = stderr_reader.next_line() => {
+                    if let Some(l) = line.context("failed to read port-forward stderr")? {
+                        debug!("[port-forward] {}", l);
+                        last_error = Some(l);
+                    }
+                }
+                status = child.wait() => {
+                    let status = status.context("failed to wait for port-forward process")?;
+                    break Err(match &last_error {
+                        Some(err) => anyhow!("kubectl port-forward exited early with status {}: {}", status, err),
+                        None => anyhow!("kubectl port-forward exited early with status {}", status),
+                    });
+                }
+            }
+        }
+    })
+    .await
+    .context("timed out waiting for port-forward to assign a local port")??;
+
+    let stdout_task = tokio::spawn(async move {
+        while let Some(line) = stdout_reader.next_line().await? {
+            debug!("[port-forward] {}", line);
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+    let stderr_task = tokio::spawn(async move {
+        while let Some(line) = stderr_reader.next_line().await? {
+            debug!("[port-forward] {}", line);
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+
+    Ok((child, stdout_task, stderr_task, port))
+}
+
 fn parse_port(line: &str) -> Option<u16> {
     if !line.contains("Forwarding from") {
         return None;
@@ -1,151 +1,707 @@
 use crate::bundle;
-use crate::cli::ProxyArgs;
+use crate::cli::{ForwardArgs, ProxyArgs, TargetArgs};
+use crate::config;
+use crate::error::SshpodError;
 use crate::hostspec::{self, Target};
 use crate::keys;
-use crate::kubectl::{self, RemoteTarget};
-use crate::port_forward::PortForward;
+use crate::kubectl::{self, KubectlOptions, RemoteTarget};
+use crate::port_forward::{ForwardTarget, PortForward};
 use crate::proxy_io;
 use crate::remote;
-use anyhow::{bail, Context, Result};
-use log::info;
+use crate::resolve;
+use crate::selector;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info, warn};
+use sha2::{Digest, Sha256};
 use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-fn init_logger(level_arg: &str) {
+pub fn init_logger(level_arg: &str) {
     let mut builder = env_logger::Builder::new();
     builder.format(|buf, record| writeln!(buf, "{}", record.args()));
     builder.parse_filters(level_arg);
     let _ = builder.try_init();
 }
 
-async fn resolve_remote_target(
+// Like `init_logger`, but also appends every record to `path`, so several
+// connections can share one file.
+fn init_logger_with_file(level_arg: &str, path: &Path) -> Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open log file {}", path.display()))?;
+    let mut builder = env_logger::Builder::new();
+    builder.format(|buf, record| writeln!(buf, "{}", record.args()));
+    builder.parse_filters(level_arg);
+    builder.target(env_logger::Target::Pipe(Box::new(Tee(file))));
+    let _ = builder.try_init();
+    Ok(())
+}
+
+struct Tee(std::fs::File);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // The file is the record that matters; stderr may already be gone.
+        let _ = std::io::stderr().write_all(buf);
+        self.0.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let _ = std::io::stderr().flush();
+        self.0.flush()
+    }
+}
+
+pub fn kubectl_options(host: &hostspec::HostSpec, args: &TargetArgs) -> KubectlOptions {
+    KubectlOptions {
+        context: host.context.clone(),
+        kubeconfig: args.kubeconfig.clone(),
+        impersonate: args.impersonate.clone(),
+        retries: args.kubectl_retries,
+    }
+}
+
+pub fn parse_host(args: &TargetArgs) -> Result<hostspec::HostSpec> {
+    let mut host = parse_hostspec(&args.host)?;
+    apply_config_defaults(&mut host);
+    Ok(host)
+}
+
+fn parse_hostspec(hostname: &str) -> Result<hostspec::HostSpec> {
+    match hostspec::parse(hostname) {
+        Ok(host) => Ok(host),
+        Err(err) => {
+            let suggestions = hostspec::suggest_corrections(hostname);
+            let err = anyhow::Error::new(SshpodError::HostSpecParse(err));
+            if suggestions.is_empty() {
+                return Err(err.context("failed to parse hostspec"));
+            }
+            Err(err.context(format!(
+                "failed to parse hostspec (did you mean {}?)",
+                suggestions.join(", ")
+            )))
+        }
+    }
+}
+
+fn apply_config_defaults(host: &mut hostspec::HostSpec) {
+    let config = config::get();
+    if host.context.is_none() {
+        host.context = config.default_context.clone();
+    }
+    if host.namespace.is_none() {
+        host.namespace = config.default_namespace.clone();
+    }
+}
+
+// A flag beats the hostspec segment; saying both is likely a mistake, so warn.
+fn override_segment(field: &mut Option<String>, flag: Option<&str>, segment: &str) {
+    let Some(value) = flag else {
+        return;
+    };
+    if let Some(current) = field.as_deref().filter(|current| *current != value) {
+        warn!(
+            "[sshpod] --{} {} overrides {}--{} from the hostspec",
+            segment, value, segment, current
+        );
+    }
+    *field = Some(value.to_string());
+}
+
+pub async fn resolve_from_args(args: &TargetArgs) -> Result<(RemoteTarget, kubectl::PodInfo)> {
+    let host = parse_host(args)?;
+    resolve_host(&host, args).await
+}
+
+async fn resolve_host(
     host: &hostspec::HostSpec,
+    args: &TargetArgs,
 ) -> Result<(RemoteTarget, kubectl::PodInfo)> {
-    if let Some(ctx) = &host.context {
-        kubectl::ensure_context_exists(ctx).await?;
-    }
-    let namespace = if let Some(ns) = host.namespace.clone() {
-        ns
-    } else if let Some(ctx) = &host.context {
-        kubectl::get_context_namespace(ctx)
-            .await?
-            .unwrap_or_default()
-    } else {
-        kubectl::get_context_namespace("default")
-            .await?
-            .unwrap_or_default()
-    };
-    let ns_str = namespace.as_str();
+    debug!("[sshpod] parsed hostspec: {}", host.to_hostname());
+    let opts = kubectl_options(host, args);
+    resolve::resolve_remote_target_with_wait(host, &opts, args.wait).await
+}
 
-    let pod_name = match &host.target {
-        Target::Pod(pod) => pod.clone(),
-        Target::Deployment(dep) => {
-            kubectl::choose_pod_for_deployment(host.context.as_deref(), ns_str, dep)
-                .await
-                .with_context(|| format!("failed to select pod from deployment `{}`", dep))?
+// Reconnecting is only safe before any SSH bytes have been relayed: once the
+// session is underway a fresh TCP connection would be a new, unrelated sshd
+// session. So the port-forward is retried until sshd's banner comes through,
+// and the banner is handed back for the caller to write out first.
+async fn connect_forwarded(
+    forward: &mut PortForward,
+    mut local_port: u16,
+    retries: u32,
+) -> Result<(TcpStream, Vec<u8>)> {
+    let mut attempt = 0;
+    loop {
+        match read_banner(local_port).await {
+            Ok(connected) => return Ok(connected),
+            Err(err) if attempt < retries => {
+                attempt += 1;
+                warn!(
+                    "[sshpod] port-forward dropped ({:#}); reconnecting ({}/{})",
+                    err, attempt, retries
+                );
+                local_port = forward.reconnect().await?;
+            }
+            Err(err) => return Err(err),
         }
-        Target::Job(job) => kubectl::choose_pod_for_job(host.context.as_deref(), ns_str, job)
-            .await
-            .with_context(|| format!("failed to select pod from job `{}`", job))?,
-    };
-    info!(
-        "[sshpod] resolved pod: {} (namespace={}, context={})",
-        pod_name,
-        ns_str,
-        host.context.as_deref().unwrap_or("default")
-    );
+    }
+}
 
-    let pod_info = kubectl::get_pod_info(host.context.as_deref(), ns_str, &pod_name)
+async fn read_banner(local_port: u16) -> Result<(TcpStream, Vec<u8>)> {
+    let mut stream = TcpStream::connect(("127.0.0.1", local_port))
         .await
-        .with_context(|| format!("failed to inspect pod {}.{}", pod_name, ns_str))?;
-
-    let container = match host.container.as_ref() {
-        Some(c) => {
-            if pod_info.containers.iter().any(|name| name == c) {
-                c.clone()
-            } else {
-                bail!("container `{}` not found in pod {}", c, pod_name);
-            }
+        .context("failed to connect to forwarded sshd port")?;
+    let mut buf = vec![0u8; 256];
+    let n = tokio::time::timeout(Duration::from_secs(10), stream.read(&mut buf))
+        .await
+        .context("timed out waiting for the sshd banner")?
+        .context("failed to read from forwarded sshd port")?;
+    if n == 0 {
+        bail!("forwarded connection closed before sshd sent its banner");
+    }
+    buf.truncate(n);
+    Ok((stream, buf))
+}
+
+pub const REMOTE_ROOT: &str = "/tmp/sshpod";
+const MAX_STALE_BASES: usize = 3;
+
+// Which setup phase `run` is in and how long the finished ones took; names
+// the phase in the --connect-timeout error and feeds the debug timing log.
+#[derive(Default)]
+struct SetupPhases(std::sync::Mutex<PhaseLog>);
+
+#[derive(Default)]
+struct PhaseLog {
+    current: Option<(&'static str, Instant)>,
+    done: Vec<(&'static str, Duration)>,
+}
+
+impl PhaseLog {
+    fn close(&mut self) {
+        if let Some((phase, started)) = self.current.take() {
+            let elapsed = started.elapsed();
+            debug!("[sshpod][timing] {}: {}ms", phase, elapsed.as_millis());
+            self.done.push((phase, elapsed));
         }
-        None => {
-            if pod_info.containers.len() == 1 {
-                pod_info.containers[0].clone()
-            } else {
-                bail!("This Pod has multiple containers. Use container--<container>.pod--<pod>.namespace--<namespace>[.context--<context>].sshpod to specify the target container.");
+    }
+}
+
+impl SetupPhases {
+    fn log(&self) -> std::sync::MutexGuard<'_, PhaseLog> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn enter(&self, phase: &'static str) {
+        let mut log = self.log();
+        log.close();
+        log.current = Some((phase, Instant::now()));
+    }
+
+    fn finish(&self) {
+        self.log().close();
+    }
+
+    fn current(&self) -> &'static str {
+        self.log().current.map_or("setup", |(phase, _)| phase)
+    }
+
+    fn log_summary(&self) {
+        if !log::log_enabled!(log::Level::Debug) {
+            return;
+        }
+        let log = self.log();
+        debug!("[sshpod][timing] {:<20} {:>8}", "phase", "ms");
+        for (phase, elapsed) in &log.done {
+            debug!("[sshpod][timing] {:<20} {:>8}", phase, elapsed.as_millis());
+        }
+        let total: Duration = log.done.iter().map(|(_, elapsed)| *elapsed).sum();
+        debug!("[sshpod][timing] {:<20} {:>8}", "total", total.as_millis());
+    }
+}
+
+// Same range as the random ports picked in the pod.
+fn deterministic_port(pod_uid: &str) -> u16 {
+    let digest = Sha256::digest(pod_uid.as_bytes());
+    let hash = u64::from_be_bytes(digest[..8].try_into().expect("sha256 is 32 bytes"));
+    20000 + (hash % 45000) as u16
+}
+
+pub fn remote_base(root: &str, pod_uid: &str, container: &str) -> String {
+    format!("{}/{}/{}", root.trim_end_matches('/'), pod_uid, container)
+}
+
+// Where sshd lives for this target: `--base-dir` when given, used as is since
+// the user picked it and it is theirs to keep unique, the per-pod default otherwise.
+pub fn target_base(args: &TargetArgs, pod_uid: &str, container: &str) -> Result<String> {
+    match &args.base_dir {
+        Some(dir) if !dir.starts_with('/') || dir.trim_end_matches('/').is_empty() => {
+            bail!("--base-dir must be an absolute path below / in the container")
+        }
+        Some(dir) => Ok(dir.trim_end_matches('/').to_string()),
+        None => Ok(remote_base(REMOTE_ROOT, pod_uid, container)),
+    }
+}
+
+pub async fn cleanup(args: TargetArgs) -> Result<()> {
+    init_logger(&args.log_level);
+    let (target, pod_info) = resolve_from_args(&args).await?;
+    let base = target_base(&args, &pod_info.uid, &target.container)?;
+    bundle::cleanup_remote(&target, &base).await?;
+    println!("Removed {} from pod {}", base, target.pod);
+    Ok(())
+}
+
+pub async fn status(args: TargetArgs) -> Result<()> {
+    init_logger(&args.log_level);
+    let state = match resolve_from_args(&args).await {
+        Ok((target, pod_info)) => match target_base(&args, &pod_info.uid, &target.container) {
+            Ok(base) => remote::sshd_status(&target, &base).await,
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err),
+    };
+    let state = match state {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            std::process::exit(2);
+        }
+    };
+
+    match (state.running, state.port) {
+        (true, Some(port)) => println!("sshd running on port {}", port),
+        (true, None) => println!("sshd running (port unknown)"),
+        _ => println!("sshd not running"),
+    }
+    match (&state.bundle_version, state.bundle_age_secs) {
+        (Some(version), Some(age)) => {
+            println!("bundle {} (installed {} ago)", version, format_age(age))
+        }
+        (Some(version), None) => println!("bundle {}", version),
+        (None, _) => println!("bundle not installed"),
+    }
+    if !state.running {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+pub async fn forward(args: ForwardArgs) -> Result<()> {
+    init_logger(&args.target.log_level);
+    // Services are forwarded as such so Kubernetes keeps choosing the pod.
+    let host = parse_host(&args.target)?;
+    let (opts, namespace, forward_target) = match &host.target {
+        Target::Service(service) => {
+            let opts = kubectl_options(&host, &args.target);
+            if let Some(ctx) = &host.context {
+                kubectl::ensure_context_exists(ctx, &opts).await?;
             }
+            let namespace = kubectl::resolve_namespace(
+                &opts,
+                host.namespace.as_deref(),
+                host.context.as_deref(),
+            )
+            .await?;
+            (opts, namespace, ForwardTarget::Service(service.clone()))
+        }
+        _ => {
+            let (target, _) = resolve_from_args(&args.target).await?;
+            (
+                target.opts,
+                target.namespace,
+                ForwardTarget::Pod(target.pod),
+            )
         }
     };
-    info!("[sshpod] resolved container: {}", container);
+    let (mut forward, local_port) = PortForward::start(
+        &opts,
+        &namespace,
+        forward_target.clone(),
+        args.remote_port,
+        args.local_port,
+        true,
+    )
+    .await?;
+    println!(
+        "Forwarding localhost:{} -> {}:{}",
+        local_port, forward_target, args.remote_port
+    );
 
-    let target = RemoteTarget {
-        context: host.context.clone(),
-        namespace,
-        pod: pod_name,
-        container,
+    let mut health = forward.health_watch();
+    let result = tokio::select! {
+        result = shutdown_signal() => result,
+        _ = health.wait_for(|healthy| !healthy) => {
+            Err(anyhow!("port-forward to {} stopped responding", forward_target))
+        }
     };
+    forward.stop().await?;
+    result
+}
+
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = signal(SignalKind::terminate()).context("failed to listen for SIGTERM")?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.context("failed to listen for Ctrl-C")?,
+            _ = term.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .context("failed to listen for Ctrl-C")?;
+    Ok(())
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
 
-    Ok((target, pod_info))
+// The socket is expected to reach an sshd already, e.g. one forwarded by
+// other means; nothing in the pod is touched.
+#[cfg(unix)]
+async fn relay_unix(socket: &Path) -> Result<()> {
+    info!("[sshpod] relaying over {}", socket.display());
+    let stats = proxy_io::pump_unix(socket).await?;
+    log_stats(&stats);
+    Ok(())
 }
 
+fn log_stats(stats: &proxy_io::ConnectionStats) {
+    info!(
+        "[sshpod] connection closed: sent {} bytes, received {} bytes in {:.1}s",
+        stats.bytes_sent, stats.bytes_received, stats.duration_secs
+    );
+}
+
+#[cfg(not(unix))]
+async fn relay_unix(_socket: &Path) -> Result<()> {
+    bail!("--unix-socket is only supported on Unix");
+}
+
+/// Sets up sshd in the target pod and relays stdin/stdout to it, as `sshpod proxy` does.
 pub async fn run(args: ProxyArgs) -> Result<()> {
-    init_logger(&args.log_level);
-    let host = hostspec::parse(&args.host).context("failed to parse hostspec")?;
+    match &args.log_file {
+        Some(path) => init_logger_with_file(&args.target.log_level, path)?,
+        None => init_logger(&args.target.log_level),
+    }
+    if let Some(socket) = &args.unix_socket {
+        return relay_unix(socket).await;
+    }
+    selector::init(args.pod_selector);
     let login_user = args
         .user
         .filter(|u| !u.is_empty())
         .unwrap_or_else(whoami::username);
 
-    let (target, pod_info) = resolve_remote_target(&host).await?;
+    let mut host = parse_hostspec(&args.target.host)?;
+    override_segment(&mut host.context, args.context.as_deref(), "context");
+    override_segment(&mut host.namespace, args.namespace.as_deref(), "namespace");
+    if args.container.is_some() {
+        override_segment(&mut host.container, args.container.as_deref(), "container");
+        host.init_container = false;
+    }
+    apply_config_defaults(&mut host);
+    let (target, pod_info) = resolve_host(&host, &args.target).await?;
     let ns_str = target.namespace.as_str();
     let pod_name = target.pod.clone();
-    let container = target.container.clone();
-    let base = format!("/tmp/sshpod/{}/{}", pod_info.uid, container);
+    let base = target_base(&args.target, &pod_info.uid, &target.container)?;
 
-    let local_key = keys::ensure_key("id_ed25519")
-        .await
-        .context("failed to ensure ~/.cache/sshpod/id_ed25519 exists")?;
-    let host_keys = keys::ensure_key("ssh_host_ed25519_key")
+    let key_name = args.key_type.client_key_name();
+    let key_scope = if args.per_context_key {
+        target.opts.context.as_deref()
+    } else {
+        None
+    };
+    let key_path = keys::key_dir(key_scope)?.join(key_name);
+    let local_key = match &args.identity_file {
+        Some(path) => keys::import_existing(path)
+            .await
+            .with_context(|| format!("failed to import {}", path.display()))?,
+        None => keys::rotate_if_stale(key_name, args.key_type, key_scope, args.key_max_age_days)
+            .await
+            .with_context(|| format!("failed to ensure {} exists", key_path.display()))?,
+    };
+    if key_scope.is_some() {
+        debug!("[sshpod] using per-context key {}", key_path.display());
+    }
+    let host_keys = keys::ensure_key("ssh_host_ed25519_key", None)
         .await
         .context("failed to create host keys")?;
 
-    remote::try_acquire_lock(&target, &base).await;
     remote::assert_login_user_allowed(&target, &login_user).await?;
 
-    let arch = bundle::detect_remote_arch(&target)
-        .await
-        .context("failed to detect remote arch")?;
-    info!("[sshpod] remote architecture: {}", arch);
-    bundle::ensure_bundle(&target, &base, &arch).await?;
-    info!("[sshpod] sshd bundle ready for pod {}", pod_name);
-    remote::install_host_keys(&target, &base, &host_keys).await?;
-
-    info!("[sshpod] starting/ensuring sshd in pod {}", pod_name);
-    let remote_port =
-        remote::ensure_sshd_running(&target, &base, &login_user, &local_key.public).await?;
-    info!(
-        "[sshpod] sshd is listening on 127.0.0.1:{} (pod {})",
-        remote_port, pod_name
-    );
+    let phases = SetupPhases::default();
+    let setup = async {
+        let mut extra_keys = Vec::new();
+        for path in &args.authorized_keys {
+            extra_keys.push(
+                tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+            );
+        }
+        let mut authorized: Vec<&str> = vec![&local_key.public];
+        authorized.extend(extra_keys.iter().flat_map(|keys| keys.lines()));
+        authorized.retain(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
 
-    info!(
-        "[sshpod] starting port-forward to {}:{}",
-        pod_name, remote_port
-    );
-    let (mut forward, local_port) =
-        PortForward::start(host.context.as_deref(), ns_str, &pod_name, remote_port).await?;
+        phases.enter("session check");
+        // The client key may have been rotated since an existing sshd started.
+        remote::ensure_sshd_config_updated(&target, &base, &login_user, &authorized).await?;
+        let remote_port = match remote::check_existing_session(&target, &base).await? {
+            Some(port) => {
+                info!(
+                    "[sshpod] reusing sshd already listening on 127.0.0.1:{} (pod {})",
+                    port, pod_name
+                );
+                port
+            }
+            None => {
+                if args.target.base_dir.is_none() {
+                    remote::remove_stale_bases(
+                        &target,
+                        REMOTE_ROOT,
+                        &pod_info.uid,
+                        MAX_STALE_BASES,
+                    )
+                    .await;
+                }
+                remote::assert_container_writable(&target, &base).await?;
+                remote::check_lock_staleness(&target, &base, remote::DEFAULT_LOCK_MAX_AGE_SECS)
+                    .await?;
+                remote::try_acquire_lock(&target, &base).await;
+                phases.enter("arch detection");
+                let arch = bundle::detect_remote_arch(&target)
+                    .await
+                    .context("failed to detect remote arch")?;
+                info!("[sshpod] remote architecture: {}", arch);
+                phases.enter("bundle check");
+                remote::check_disk_space(&target, &base, bundle::REQUIRED_DISK_MB).await?;
+                remote::assert_sshd_binary_intact(&target, &base).await?;
+                let installed = bundle::ensure_bundle(&target, &base, &arch).await?;
+                // A fresh install usually means sshpod was upgraded; the pod's other
+                // containers may still hold older bundles. Only the default layout
+                // has siblings that are sshpod's to remove.
+                if installed && args.target.base_dir.is_none() {
+                    let pod_root = format!("{}/{}", REMOTE_ROOT, pod_info.uid);
+                    if let Err(err) = bundle::ensure_bundle_cleanup_old(&target, &pod_root).await {
+                        debug!("[sshpod] failed to clean up old bundles: {:#}", err);
+                    }
+                }
+                info!("[sshpod] sshd bundle ready for pod {}", pod_name);
+                phases.enter("key install");
+                remote::install_host_keys(&target, &base, &host_keys).await?;
+                remote::install_authorized_keys(&target, &base, &authorized).await?;
+
+                let sshd_port = match args.sshd_port {
+                    Some(port) => remote::SshdPort::Pinned(port),
+                    None if args.deterministic_port => {
+                        remote::SshdPort::Preferred(deterministic_port(&pod_info.uid))
+                    }
+                    None => remote::SshdPort::Random,
+                };
+                phases.enter("os detection");
+                let os_info = remote::get_remote_os_info(&target).await?;
+                debug!(
+                    "[sshpod] remote OS: {} (nologin={}, user tool={:?})",
+                    os_info.distro, os_info.nologin_path, os_info.user_add
+                );
+
+                phases.enter("sshd start");
+                info!("[sshpod] starting/ensuring sshd in pod {}", pod_name);
+                let remote_port = remote::ensure_sshd_running(
+                    &target,
+                    &base,
+                    &login_user,
+                    &os_info,
+                    sshd_port,
+                    args.target.base_dir.is_none(),
+                    Duration::from_secs(args.sshd_startup_timeout_secs),
+                )
+                .await?;
+                info!(
+                    "[sshpod] sshd is listening on 127.0.0.1:{} (pod {})",
+                    remote_port, pod_name
+                );
+                remote::cleanup_lock(&target, &base).await?;
+                remote_port
+            }
+        };
+
+        phases.enter("port-forward start");
+        info!(
+            "[sshpod] starting port-forward to {}:{}",
+            pod_name, remote_port
+        );
+        let (forward, local_port) = PortForward::start(
+            &target.opts,
+            ns_str,
+            ForwardTarget::Pod(pod_name.clone()),
+            remote_port,
+            args.local_port,
+            // Probing would add sshd.log lines for the whole session; the
+            // session's own traffic shows a stuck forward soon enough.
+            false,
+        )
+        .await?;
+        Ok::<_, anyhow::Error>((forward, local_port, remote_port))
+    };
+    let (mut forward, local_port, remote_port) = if args.connect_timeout_secs == 0 {
+        setup.await?
+    } else {
+        match tokio::time::timeout(Duration::from_secs(args.connect_timeout_secs), setup).await {
+            Ok(result) => result?,
+            Err(_) => bail!(
+                "connection setup timed out after {}s during {}",
+                args.connect_timeout_secs,
+                phases.current()
+            ),
+        }
+    };
     info!(
         "[sshpod] port-forward established: localhost:{} -> {}:{}",
         local_port, pod_name, remote_port
     );
 
-    let stream = TcpStream::connect(("127.0.0.1", local_port))
-        .await
-        .context("failed to connect to forwarded sshd port")?;
+    if args.dry_run {
+        phases.finish();
+        phases.log_summary();
+        println!("pod: {}/{}", ns_str, pod_name);
+        println!("container: {}", target.container);
+        println!("local_port: {}", local_port);
+        println!("remote_port: {}", remote_port);
+        return forward.stop().await;
+    }
 
-    let pump_result = proxy_io::pump(stream).await;
+    phases.enter("tcp connect");
+    let (stream, banner) =
+        match connect_forwarded(&mut forward, local_port, args.reconnect_retries).await {
+            Ok(connected) => connected,
+            Err(err) => {
+                let _ = forward.stop().await;
+                return Err(err);
+            }
+        };
+    phases.finish();
+    phases.log_summary();
+    let mut stdout = tokio::io::stdout();
+    stdout.write_all(&banner).await?;
+    stdout.flush().await?;
+
+    let pump = async {
+        match args.max_bandwidth_kbps.filter(|kbps| *kbps > 0) {
+            Some(kbps) => proxy_io::pump_throttled(stream, kbps.saturating_mul(1000) / 8).await,
+            None if args.idle_timeout_secs > 0 => {
+                proxy_io::pump_with_idle_timeout(stream, args.idle_timeout_secs).await
+            }
+            None => proxy_io::pump(stream).await,
+        }
+    };
+    let mut health = forward.health_watch();
+    let pump_result = tokio::select! {
+        result = pump => result.map(Some),
+        _ = health.wait_for(|healthy| !healthy) => {
+            let cause = match remote::check_sshd_alive(&target, &base).await {
+                Ok(false) => " (sshd in the pod is no longer running)",
+                _ => "",
+            };
+            Err(anyhow!("port-forward to {} stopped responding{}", pod_name, cause))
+        }
+        result = shutdown_signal() => result.map(|_| None),
+    };
     let stop_result = forward.stop().await;
 
-    pump_result?;
+    match pump_result? {
+        Some(stats) if stats.idle_closed => {
+            log_stats(&stats);
+            stop_result?;
+            // Exiting closes stdout, which is what makes ssh end the session;
+            // see below for why returning would hang instead.
+            std::process::exit(0);
+        }
+        Some(stats) => log_stats(&stats),
+        None => {
+            stop_result?;
+            info!("[sshpod] interrupted; port-forward stopped");
+            // The stdin reader sits on a blocking thread that would keep the
+            // runtime alive until ssh closes the pipe.
+            std::process::exit(0);
+        }
+    }
     stop_result?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_record_each_finished_step() {
+        let phases = SetupPhases::default();
+        assert_eq!(phases.current(), "setup");
+        phases.enter("arch detection");
+        phases.enter("bundle check");
+        assert_eq!(phases.current(), "bundle check");
+        phases.finish();
+        let done: Vec<&str> = phases.log().done.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(done, ["arch detection", "bundle check"]);
+    }
+
+    #[test]
+    fn flags_override_hostspec_segments() {
+        let mut field = Some("dev".to_string());
+        override_segment(&mut field, None, "namespace");
+        assert_eq!(field.as_deref(), Some("dev"));
+        override_segment(&mut field, Some("prod"), "namespace");
+        assert_eq!(field.as_deref(), Some("prod"));
+        let mut unset = None;
+        override_segment(&mut unset, Some("staging"), "context");
+        assert_eq!(unset.as_deref(), Some("staging"));
+    }
+
+    #[test]
+    fn deterministic_port_is_stable_and_in_range() {
+        let uid = "0f1e2d3c-4b5a-6978-8796-a5b4c3d2e1f0";
+        let port = deterministic_port(uid);
+        assert_eq!(port, deterministic_port(uid));
+        assert!((20000..65000).contains(&port));
+        assert_ne!(port, deterministic_port("another-uid"));
+    }
+
+    #[test]
+    fn target_base_honours_base_dir() {
+        let mut args = TargetArgs {
+            host: "web.ns.sshpod".to_string(),
+            kubeconfig: None,
+            impersonate: None,
+            kubectl_retries: 0,
+            wait: None,
+            base_dir: None,
+            log_level: "info".to_string(),
+        };
+        assert_eq!(
+            target_base(&args, "uid", "app").unwrap(),
+            "/tmp/sshpod/uid/app"
+        );
+        args.base_dir = Some("/data/sshpod/".to_string());
+        assert_eq!(target_base(&args, "uid", "app").unwrap(), "/data/sshpod");
+        args.base_dir = Some("relative".to_string());
+        assert!(target_base(&args, "uid", "app").is_err());
+    }
+}
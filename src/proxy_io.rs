@@ -1,9 +1,59 @@
-use anyhow::Result;
-use tokio::io::{self, AsyncWriteExt};
+use anyhow::{Context, Result};
+use log::{debug, info};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::time::{interval, sleep, Duration, Instant};
 
-pub async fn pump(stream: TcpStream) -> Result<()> {
-    let (mut reader, mut writer) = stream.into_split();
+const COPY_CHUNK: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConnectionStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub duration_secs: f64,
+    /// The idle timeout ended the relay, not either side closing.
+    pub idle_closed: bool,
+}
+
+impl ConnectionStats {
+    fn since(started: Instant, bytes_sent: u64, bytes_received: u64) -> Self {
+        let stats = ConnectionStats {
+            bytes_sent,
+            bytes_received,
+            duration_secs: started.elapsed().as_secs_f64(),
+            idle_closed: false,
+        };
+        debug!("[sshpod][proxy_io] {:?}", stats);
+        stats
+    }
+}
+
+pub async fn pump(stream: TcpStream) -> Result<ConnectionStats> {
+    let (reader, writer) = stream.into_split();
+    relay(reader, writer).await
+}
+
+#[cfg(unix)]
+pub async fn pump_unix(socket_path: &Path) -> Result<ConnectionStats> {
+    let stream = UnixStream::connect(socket_path)
+        .await
+        .with_context(|| format!("failed to connect to {}", socket_path.display()))?;
+    let (reader, writer) = stream.into_split();
+    relay(reader, writer).await
+}
+
+async fn relay<R, W>(mut reader: R, mut writer: W) -> Result<ConnectionStats>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let started = Instant::now();
     let mut stdin = io::stdin();
     let mut stdout = io::stdout();
 
@@ -20,12 +70,256 @@ pub async fn pump(stream: TcpStream) -> Result<()> {
     });
 
     let (a, b) = tokio::join!(to_remote, from_remote);
-    let to_bytes = a??;
-    let from_bytes = b??;
-    // Debug logging kept compact
-    eprintln!(
-        "[sshpod][proxy_io] bytes_to_remote={} bytes_from_remote={}",
-        to_bytes, from_bytes
-    );
-    Ok(())
+    Ok(ConnectionStats::since(started, a??, b??))
+}
+
+// Both directions draw from one bucket, so the limit caps the total traffic.
+pub async fn pump_throttled(stream: TcpStream, max_bytes_per_sec: u64) -> Result<ConnectionStats> {
+    let started = Instant::now();
+    let (mut reader, mut writer) = stream.into_split();
+    let mut stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let bucket = Arc::new(Mutex::new(TokenBucket::new(max_bytes_per_sec)));
+
+    let up_bucket = bucket.clone();
+    let to_remote = tokio::spawn(async move {
+        let copied = copy_paced(&mut stdin, &mut writer, Some(&up_bucket), None).await?;
+        writer.shutdown().await?;
+        Ok::<_, anyhow::Error>(copied)
+    });
+
+    let from_remote = tokio::spawn(async move {
+        let copied = copy_paced(&mut reader, &mut stdout, Some(&bucket), None).await?;
+        stdout.flush().await?;
+        Ok::<_, anyhow::Error>(copied)
+    });
+
+    let (a, b) = tokio::join!(to_remote, from_remote);
+    Ok(ConnectionStats::since(started, a??, b??))
+}
+
+// Stops relaying once neither side has sent anything for `idle_secs` and
+// reports it via `idle_closed`. The stdin reader is stuck on a blocking thread
+// and stdout stays open, so ssh only notices once the caller exits the process.
+pub async fn pump_with_idle_timeout(stream: TcpStream, idle_secs: u64) -> Result<ConnectionStats> {
+    let (reader, writer) = stream.into_split();
+    let idle = Duration::from_secs(idle_secs);
+    relay_with_idle_timeout(reader, writer, io::stdin(), io::stdout(), idle).await
+}
+
+async fn relay_with_idle_timeout<R, W, I, O>(
+    mut reader: R,
+    mut writer: W,
+    mut input: I,
+    mut output: O,
+    idle: Duration,
+) -> Result<ConnectionStats>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+    I: AsyncRead + Unpin + Send + 'static,
+    O: AsyncWrite + Unpin + Send + 'static,
+{
+    let started = Instant::now();
+    let up = Arc::new(Meter::new());
+    let down = Arc::new(Meter::new());
+
+    let up_meter = up.clone();
+    let mut to_remote = tokio::spawn(async move {
+        let copied = copy_paced(&mut input, &mut writer, None, Some(&up_meter)).await?;
+        writer.shutdown().await?;
+        Ok::<_, anyhow::Error>(copied)
+    });
+
+    let down_meter = down.clone();
+    let mut from_remote = tokio::spawn(async move {
+        let copied = copy_paced(&mut reader, &mut output, None, Some(&down_meter)).await?;
+        output.flush().await?;
+        Ok::<_, anyhow::Error>(copied)
+    });
+
+    let watchdog = async {
+        let mut ticker = interval(idle.min(Duration::from_secs(1)));
+        loop {
+            ticker.tick().await;
+            let last = up.last_activity().max(down.last_activity());
+            if last.elapsed() >= idle {
+                return;
+            }
+        }
+    };
+
+    tokio::select! {
+        (a, b) = async { tokio::join!(&mut to_remote, &mut from_remote) } => {
+            Ok(ConnectionStats::since(started, a??, b??))
+        }
+        _ = watchdog => {
+            to_remote.abort();
+            from_remote.abort();
+            info!("[sshpod] closing connection after {}s without traffic", idle.as_secs());
+            Ok(ConnectionStats {
+                idle_closed: true,
+                ..ConnectionStats::since(started, up.bytes(), down.bytes())
+            })
+        }
+    }
+}
+
+// Per-direction byte count and time of the last write, for the idle watchdog.
+struct Meter {
+    bytes: AtomicU64,
+    last_activity: Mutex<Instant>,
+}
+
+impl Meter {
+    fn new() -> Self {
+        Meter {
+            bytes: AtomicU64::new(0),
+            last_activity: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn record(&self, bytes: usize) {
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        *self.last_activity.lock().expect("meter lock poisoned") = Instant::now();
+    }
+
+    fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    fn last_activity(&self) -> Instant {
+        *self.last_activity.lock().expect("meter lock poisoned")
+    }
+}
+
+async fn copy_paced<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    bucket: Option<&Mutex<TokenBucket>>,
+    meter: Option<&Meter>,
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // Small reads keep low limits smooth instead of one long pause per chunk.
+    let chunk = match bucket {
+        Some(bucket) => bucket.lock().expect("token bucket lock poisoned").rate as usize / 10,
+        None => COPY_CHUNK,
+    };
+    let mut buf = vec![0u8; chunk.clamp(1, COPY_CHUNK)];
+    let mut total = 0u64;
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(total);
+        }
+        if let Some(bucket) = bucket {
+            let wait = bucket
+                .lock()
+                .expect("token bucket lock poisoned")
+                .reserve(n as u64, Instant::now());
+            if !wait.is_zero() {
+                sleep(wait).await;
+            }
+        }
+        writer.write_all(&buf[..n]).await?;
+        writer.flush().await?;
+        if let Some(meter) = meter {
+            meter.record(n);
+        }
+        total += n as u64;
+    }
+}
+
+// Holds at most one second of traffic. Reservations may overdraw the bucket;
+// the caller then sleeps until the debt is paid back.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1) as f64;
+        TokenBucket {
+            rate,
+            tokens: rate,
+            last: None,
+        }
+    }
+
+    fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        if let Some(last) = self.last {
+            let refill = now.saturating_duration_since(last).as_secs_f64() * self.rate;
+            self.tokens = (self.tokens + refill).min(self.rate);
+        }
+        self.last = Some(now);
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_paces_after_burst() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000);
+        assert_eq!(bucket.reserve(1000, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(500));
+        // Half a second later the debt is paid; another 250 bytes waits 250ms.
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.reserve(250, later), Duration::from_millis(250));
+        // Idle time refills only up to one second's worth.
+        let much_later = later + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(1000, much_later), Duration::ZERO);
+        assert!(bucket.reserve(1, much_later) > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn idle_relay_returns_when_quiet() {
+        // Keep the far ends alive so only the watchdog can end the relay.
+        let (remote, _remote_peer) = tokio::io::duplex(64);
+        let (input, _input_peer) = tokio::io::duplex(64);
+        let (reader, writer) = tokio::io::split(remote);
+        let stats = tokio::time::timeout(
+            Duration::from_secs(5),
+            relay_with_idle_timeout(
+                reader,
+                writer,
+                input,
+                tokio::io::sink(),
+                Duration::from_millis(200),
+            ),
+        )
+        .await
+        .expect("idle relay did not return")
+        .unwrap();
+        assert!(stats.idle_closed);
+        assert_eq!(stats.bytes_sent + stats.bytes_received, 0);
+    }
+
+    #[tokio::test]
+    async fn copy_records_activity() {
+        let meter = Meter::new();
+        let before = meter.last_activity();
+        let mut input: &[u8] = b"hello";
+        let mut output = Vec::new();
+        let copied = copy_paced(&mut input, &mut output, None, Some(&meter))
+            .await
+            .unwrap();
+        assert_eq!(copied, 5);
+        assert_eq!(output, b"hello");
+        assert_eq!(meter.bytes(), 5);
+        assert!(meter.last_activity() >= before);
+    }
 }
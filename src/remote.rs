@@ -1,13 +1,56 @@
+use crate::error::SshpodError;
 use crate::keys::Key;
 use crate::kubectl::{self, RemoteTarget};
 use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
 use tokio::time::{timeout, Duration};
 
+pub const DEFAULT_LOCK_MAX_AGE_SECS: u64 = 300;
+const SSHD_LOG_STREAM_SECS: u64 = 3;
+
 pub async fn try_acquire_lock(target: &RemoteTarget, base: &str) {
-    let lock_cmd = format!("umask 077; mkdir \"{}/lock\"", base);
+    let lock_cmd =
+        format!("umask 077; mkdir \"{base}/lock\" && date +%s > \"{base}/lock/timestamp\"");
     let _ = kubectl::exec_capture_optional_target(target, &["sh", "-c", &lock_cmd]).await;
 }
 
+pub async fn cleanup_lock(target: &RemoteTarget, base: &str) -> Result<()> {
+    let lock_path = format!("{}/lock", base);
+    kubectl::exec_capture_target(target, &["rm", "-rf", &lock_path])
+        .await
+        .with_context(|| format!("failed to remove lock {}", lock_path))?;
+    Ok(())
+}
+
+pub async fn check_lock_staleness(
+    target: &RemoteTarget,
+    base: &str,
+    max_age_secs: u64,
+) -> Result<()> {
+    let script = format!(
+        r#"LOCK="{base}/lock"
+[ -d "$LOCK" ] || exit 0
+TS="$(cat "$LOCK/timestamp" 2>/dev/null || echo 0)"
+case "$TS" in ''|*[!0-9]*) TS=0 ;; esac
+NOW="$(date +%s)"
+if [ $((NOW - TS)) -gt {max_age_secs} ]; then
+  rm -rf "$LOCK"
+  echo removed
+fi
+"#
+    );
+    let output = kubectl::exec_capture_target(target, &["sh", "-c", &script])
+        .await
+        .context("failed to check lock staleness")?;
+    if output == "removed" {
+        info!(
+            "[sshpod] removed stale lock under {} (older than {}s)",
+            base, max_age_secs
+        );
+    }
+    Ok(())
+}
+
 pub async fn assert_login_user_allowed(target: &RemoteTarget, login_user: &str) -> Result<()> {
     let uid = kubectl::exec_capture_target(target, &["id", "-u"])
         .await
@@ -28,6 +71,110 @@ pub async fn assert_login_user_allowed(target: &RemoteTarget, login_user: &str)
     Ok(())
 }
 
+pub async fn assert_container_writable(target: &RemoteTarget, base: &str) -> Result<()> {
+    let probe = format!(
+        "mkdir -p \"{base}\" && touch \"{base}/.write_test\" && rm -f \"{base}/.write_test\""
+    );
+    if kubectl::exec_capture_optional_target(target, &["sh", "-c", &probe])
+        .await?
+        .is_none()
+    {
+        bail!(
+            "cannot write to {} in the container (read-only root filesystem?); \
+             pass --base-dir pointing at a writable mount such as an emptyDir volume",
+            base
+        );
+    }
+    Ok(())
+}
+
+// A missing, empty or non-executable sshd (e.g. truncated by the storage layer)
+// drops VERSION so that ensure_bundle uploads the bundle again.
+pub async fn assert_sshd_binary_intact(target: &RemoteTarget, base: &str) -> Result<()> {
+    let script = format!(
+        "if [ -f \"{base}/bundle/VERSION\" ] && \
+         ! {{ test -x \"{base}/bundle/sshd\" && test -s \"{base}/bundle/sshd\"; }}; then \
+         rm -f \"{base}/bundle/VERSION\"; echo damaged; fi"
+    );
+    let output = kubectl::exec_capture_target(target, &["sh", "-c", &script])
+        .await
+        .context("failed to check the installed sshd binary")?;
+    if output.trim() == "damaged" {
+        warn!(
+            "[sshpod] {}/bundle/sshd is missing, empty or not executable; reinstalling the bundle",
+            base
+        );
+    }
+    Ok(())
+}
+
+// Directories left by earlier pod UIDs, e.g. when /tmp lives on a volume that
+// survives a pod restart. Best effort: failures are only logged.
+pub async fn remove_stale_bases(target: &RemoteTarget, root: &str, keep_uid: &str, max: usize) {
+    let script = format!(
+        r#"ROOT="{root}"
+KEEP="{keep_uid}"
+n=0
+for dir in "$ROOT"/*/; do
+  [ -d "$dir" ] || continue
+  name="$(basename "$dir")"
+  [ "$name" = "$KEEP" ] && continue
+  [ $n -lt {max} ] || break
+  rm -rf "$dir" && echo "$name"
+  n=$((n+1))
+done
+"#
+    );
+    match kubectl::exec_capture_optional_target(target, &["sh", "-c", &script]).await {
+        Ok(Some(removed)) => {
+            for uid in removed.lines() {
+                info!(
+                    "[sshpod] removed stale {}/{} from a previous pod",
+                    root, uid
+                );
+            }
+        }
+        Ok(None) => warn!(
+            "[sshpod] failed to clean up stale directories under {}",
+            root
+        ),
+        Err(err) => warn!("[sshpod] failed to clean up stale directories: {:#}", err),
+    }
+}
+
+// Skipped quietly when the image has no df or its output is unexpected.
+pub async fn check_disk_space(target: &RemoteTarget, path: &str, required_mb: u64) -> Result<()> {
+    let Some(output) = kubectl::exec_capture_optional_target(target, &["df", "-Pm", path]).await?
+    else {
+        debug!("[sshpod] df unavailable; skipping disk space check");
+        return Ok(());
+    };
+    let Some(available) = parse_df_available_mb(&output) else {
+        debug!("[sshpod] could not parse df output: {}", output);
+        return Ok(());
+    };
+    if available < required_mb {
+        bail!(
+            "insufficient disk space in container: {} MB available, {} MB needed (under {})",
+            available,
+            required_mb,
+            path
+        );
+    }
+    Ok(())
+}
+
+fn parse_df_available_mb(output: &str) -> Option<u64> {
+    // Filesystem 1048576-blocks Used Available Capacity Mounted-on
+    output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()
+}
+
 pub async fn install_host_keys(target: &RemoteTarget, base: &str, host_keys: &Key) -> Result<()> {
     let private = &host_keys.private;
     let public = &host_keys.public;
@@ -62,23 +209,214 @@ chmod 600 "$PRIV" "$PUB"
     Ok(())
 }
 
+// Replaces authorized_keys wholesale, so keys dropped from the list (or a
+// rotated client key) stop being accepted.
+pub async fn install_authorized_keys(
+    target: &RemoteTarget,
+    base: &str,
+    keys: &[&str],
+) -> Result<()> {
+    let content = authorized_keys_content(keys)?;
+    let script = format!(
+        r#"set -eu
+BASE="{base}"
+TMP="$BASE/.authorized_keys.tmp"
+umask 077
+mkdir -p "$BASE"
+cat > "$TMP" <<'__SSHPOD_KEYS__'
+{content}__SSHPOD_KEYS__
+chmod 600 "$TMP"
+mv "$TMP" "$BASE/authorized_keys"
+"#
+    );
+    kubectl::exec_with_input_target(target, &["sh", "-s"], script.as_bytes())
+        .await
+        .with_context(|| format!("failed to install authorized keys into {}", base))?;
+    Ok(())
+}
+
+// Brings an existing install in line with this connection: rewrites
+// authorized_keys only when the keys differ, and SIGHUPs a running sshd whose
+// sshd_config no longer matches the hash recorded when it was last seen.
+pub async fn ensure_sshd_config_updated(
+    target: &RemoteTarget,
+    base: &str,
+    login_user: &str,
+    keys: &[&str],
+) -> Result<()> {
+    let content = authorized_keys_content(keys)?;
+    let script = format!(
+        r#"set -eu
+BASE="$1"
+LOGIN_USER="$2"
+[ -d "$BASE" ] || exit 0
+umask 077
+TMP="$BASE/.authorized_keys.tmp"
+cat > "$TMP" <<'__SSHPOD_KEYS__'
+{content}__SSHPOD_KEYS__
+if cmp -s "$TMP" "$BASE/authorized_keys"; then
+  rm -f "$TMP"
+else
+  chmod 600 "$TMP"
+  if [ -n "$LOGIN_USER" ]; then chown "$LOGIN_USER":"$LOGIN_USER" "$TMP" || true; fi
+  mv "$TMP" "$BASE/authorized_keys"
+  echo keys-updated
+fi
+CONFIG="$BASE/sshd_config"
+[ -f "$CONFIG" ] || exit 0
+HASH="$( (sha256sum "$CONFIG" 2>/dev/null || cksum "$CONFIG") | cut -d' ' -f1)"
+OLD="$(cat "$CONFIG.sha256" 2>/dev/null || true)"
+echo "$HASH" > "$CONFIG.sha256"
+if [ -n "$OLD" ] && [ "$OLD" != "$HASH" ] && [ -f "$BASE/sshd.pid" ] \
+   && kill -HUP "$(cat "$BASE/sshd.pid")" 2>/dev/null; then
+  echo reloaded
+fi
+"#
+    );
+    let output = kubectl::exec_with_input_target(
+        target,
+        &["sh", "-s", "--", base, login_user],
+        script.as_bytes(),
+    )
+    .await
+    .with_context(|| format!("failed to update the sshd configuration in {}", base))?;
+    for line in output.lines() {
+        match line.trim() {
+            "keys-updated" => info!("[sshpod] updated authorized_keys in {}", base),
+            "reloaded" => info!("[sshpod] sshd_config changed; sent SIGHUP to sshd"),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn authorized_keys_content(keys: &[&str]) -> Result<String> {
+    let mut lines: Vec<&str> = Vec::new();
+    for key in keys {
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("refusing to authorize an empty public key");
+        }
+        if key.contains('\n') || key.contains('\r') {
+            bail!("public key spans multiple lines: {}", key);
+        }
+        if !lines.contains(&key) {
+            lines.push(key);
+        }
+    }
+    if lines.is_empty() {
+        bail!("no public keys to authorize");
+    }
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+/// Port for a newly started sshd; a running one is reused whatever its port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SshdPort {
+    Random,
+    /// Tried first, then random ports.
+    Preferred(u16),
+    /// The only port tried.
+    Pinned(u16),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OsInfo {
+    pub distro: String,
+    pub nologin_path: String,
+    /// `useradd` or `adduser`, whichever the image has.
+    pub user_add: Option<String>,
+}
+
+pub async fn get_remote_os_info(target: &RemoteTarget) -> Result<OsInfo> {
+    let script = r#"cat /etc/os-release 2>/dev/null
+echo "SSHPOD_NOLOGIN=$(command -v nologin || command -v false)"
+echo "SSHPOD_USERADD=$(command -v useradd || command -v adduser)"
+"#;
+    let output = kubectl::exec_capture_target(target, &["sh", "-c", script])
+        .await
+        .context("failed to detect the remote OS")?;
+    Ok(parse_os_info(&output))
+}
+
+fn parse_os_info(output: &str) -> OsInfo {
+    let mut info = OsInfo {
+        distro: "unknown".to_string(),
+        nologin_path: "/bin/false".to_string(),
+        user_add: None,
+    };
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        if value.is_empty() {
+            continue;
+        }
+        match key {
+            "ID" => info.distro = value.to_string(),
+            "SSHPOD_NOLOGIN" => info.nologin_path = value.to_string(),
+            "SSHPOD_USERADD" => {
+                info.user_add = value.rsplit('/').next().map(str::to_string);
+            }
+            _ => {}
+        }
+    }
+    info
+}
+
 pub async fn ensure_sshd_running(
     target: &RemoteTarget,
     base: &str,
     login_user: &str,
-    pubkey_line: &str,
+    os: &OsInfo,
+    port: SshdPort,
+    own_parents: bool,
+    startup_timeout: Duration,
 ) -> Result<u16> {
     let script = START_SSHD_SCRIPT.as_bytes();
-    let output = timeout(Duration::from_secs(40), {
+    let user_add = os.user_add.as_deref().unwrap_or("");
+    let (port_mode, port) = match port {
+        SshdPort::Random => ("random", String::new()),
+        SshdPort::Preferred(port) => ("prefer", port.to_string()),
+        SshdPort::Pinned(port) => ("pin", port.to_string()),
+    };
+    let output = timeout(startup_timeout, {
         kubectl::exec_with_input_target(
             target,
-            &["sh", "-s", "--", base, login_user, pubkey_line],
+            &[
+                "sh",
+                "-s",
+                "--",
+                base,
+                login_user,
+                &os.nologin_path,
+                user_add,
+                port_mode,
+                &port,
+                if own_parents { "1" } else { "" },
+            ],
             script,
         )
     })
-    .await
-    .map_err(|_| anyhow::anyhow!("starting sshd timed out after 40s"))?
-    .with_context(|| format!("failed to start sshd under {}", base))?;
+    .await;
+    let output = match output {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => {
+            show_sshd_log(target, base).await;
+            return Err(err).with_context(|| format!("failed to start sshd under {}", base));
+        }
+        Err(_) => {
+            let diagnostics = collect_startup_diagnostics(target, base).await;
+            return Err(
+                anyhow::Error::new(SshpodError::SshdStartTimeout).context(format!(
+                    "starting sshd timed out after {}s{}",
+                    startup_timeout.as_secs(),
+                    diagnostics
+                )),
+            );
+        }
+    };
 
     let port: u16 = output
         .trim()
@@ -87,12 +425,171 @@ pub async fn ensure_sshd_running(
     Ok(port)
 }
 
+// Follows sshd.log (starting with its last 100 lines) on stderr until kubectl exits.
+pub async fn stream_sshd_log(target: &RemoteTarget, base: &str) -> Result<()> {
+    let log_path = format!("{}/logs/sshd.log", base);
+    let mut stderr = tokio::io::stderr();
+    kubectl::exec_stream(
+        &target.opts,
+        &target.namespace,
+        &target.pod,
+        &target.container,
+        &["tail", "-n", "100", "-f", &log_path],
+        None,
+        &mut stderr,
+    )
+    .await
+    .with_context(|| format!("failed to stream {}", log_path))?;
+    Ok(())
+}
+
+// sshd usually dies right after logging why, so a short window is enough.
+async fn show_sshd_log(target: &RemoteTarget, base: &str) {
+    eprintln!(
+        "[sshpod] sshd failed to start; last lines of {}/logs/sshd.log:",
+        base
+    );
+    let (target, base) = (target.clone(), base.to_string());
+    let task = tokio::spawn(async move { stream_sshd_log(&target, &base).await });
+    let abort = task.abort_handle();
+    match timeout(Duration::from_secs(SSHD_LOG_STREAM_SECS), task).await {
+        Ok(Ok(Err(err))) => warn!("[sshpod] {:#}", err),
+        Ok(_) => {}
+        Err(_) => abort.abort(),
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SshdStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    pub bundle_version: Option<String>,
+    pub bundle_age_secs: Option<u64>,
+}
+
+// One exec each; the connect path uses the single-script check_existing_session.
+pub async fn get_sshd_pid(target: &RemoteTarget, base: &str) -> Result<Option<u32>> {
+    let pid_path = format!("{}/sshd.pid", base);
+    let Some(pid) = kubectl::exec_capture_optional_target(target, &["cat", &pid_path]).await?
+    else {
+        return Ok(None);
+    };
+    let pid = pid
+        .trim()
+        .parse()
+        .with_context(|| format!("{} does not hold a pid: {:?}", pid_path, pid.trim()))?;
+    Ok(Some(pid))
+}
+
+pub async fn check_sshd_alive(target: &RemoteTarget, base: &str) -> Result<bool> {
+    let Some(pid) = get_sshd_pid(target, base).await? else {
+        return Ok(false);
+    };
+    let probe = format!("kill -0 {}", pid);
+    Ok(
+        kubectl::exec_capture_optional_target(target, &["sh", "-c", &probe])
+            .await?
+            .is_some(),
+    )
+}
+
+// The port of an sshd that another sshpod already started under `base`, if
+// it is still alive; lets a second connection skip the whole setup.
+pub async fn check_existing_session(target: &RemoteTarget, base: &str) -> Result<Option<u16>> {
+    let script = format!(
+        r#"B="{base}"
+[ -f "$B/sshd.pid" ] && [ -f "$B/sshd.port" ] || exit 0
+kill -0 "$(cat "$B/sshd.pid")" 2>/dev/null && cat "$B/sshd.port"
+exit 0
+"#
+    );
+    let output = kubectl::exec_capture_target(target, &["sh", "-c", &script])
+        .await
+        .with_context(|| format!("failed to check for a running sshd under {}", base))?;
+    Ok(output.trim().parse().ok())
+}
+
+pub async fn sshd_status(target: &RemoteTarget, base: &str) -> Result<SshdStatus> {
+    let script = format!(
+        r#"B="{base}"
+if [ -f "$B/sshd.pid" ] && kill -0 "$(cat "$B/sshd.pid")" 2>/dev/null; then echo running=1; fi
+if [ -f "$B/sshd.port" ]; then echo "port=$(cat "$B/sshd.port")"; fi
+if [ -f "$B/bundle/VERSION" ]; then
+  echo "version=$(cat "$B/bundle/VERSION")"
+  echo "mtime=$(stat -c %Y "$B/bundle/VERSION" 2>/dev/null)"
+fi
+echo "now=$(date +%s)"
+"#
+    );
+    let output = kubectl::exec_capture_target(target, &["sh", "-c", &script])
+        .await
+        .with_context(|| format!("failed to read sshd state under {}", base))?;
+    Ok(parse_sshd_status(&output))
+}
+
+fn parse_sshd_status(output: &str) -> SshdStatus {
+    let mut status = SshdStatus::default();
+    let mut mtime: Option<u64> = None;
+    let mut now: Option<u64> = None;
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "running" => status.running = value == "1",
+            "port" => status.port = value.parse().ok(),
+            "version" if !value.is_empty() => status.bundle_version = Some(value.to_string()),
+            "mtime" => mtime = value.parse().ok(),
+            "now" => now = value.parse().ok(),
+            _ => {}
+        }
+    }
+    if let (Some(mtime), Some(now)) = (mtime, now) {
+        status.bundle_age_secs = Some(now.saturating_sub(mtime));
+    }
+    status
+}
+
+async fn collect_startup_diagnostics(target: &RemoteTarget, base: &str) -> String {
+    let mut out = String::new();
+    let log_path = format!("{}/logs/sshd.log", base);
+    if let Ok(Some(log)) =
+        kubectl::exec_capture_optional_target(target, &["tail", "-n", "50", &log_path]).await
+    {
+        if !log.is_empty() {
+            out.push_str(&format!("\n--- {} ---\n{}", log_path, log));
+        }
+    }
+    if let Ok(logs) = kubectl::get_pod_logs(
+        &target.opts,
+        &target.namespace,
+        &target.pod,
+        &target.container,
+        50,
+    )
+    .await
+    {
+        if !logs.is_empty() {
+            out.push_str(&format!(
+                "\n--- container logs ({}) ---\n{}",
+                target.container, logs
+            ));
+        }
+    }
+    out
+}
+
 const START_SSHD_SCRIPT: &str = r#"#!/bin/sh
 set -eu
 
 BASE="$1"
 LOGIN_USER="$2"
-PUBKEY_LINE="$3"
+NOLOGIN="$3"
+USER_ADD="$4"
+PORT_MODE="${5:-random}"
+PORT_ARG="${6:-}"
+OWN_PARENTS="${7:-}"
 SSHD="$BASE/bundle/sshd"
 ENV_FILE="$BASE/environment"
 
@@ -106,9 +603,11 @@ debug_log() {
 umask 077
 mkdir -p "$BASE" "$BASE/logs" "$BASE/hostkeys"
 chmod 700 "$BASE" "$BASE/hostkeys" "$BASE/logs"
-BASE_PARENT="$(dirname "$BASE")"
-TOP_DIR="$(dirname "$BASE_PARENT")"
-chmod 711 "$TOP_DIR" "$BASE_PARENT"
+if [ -n "$OWN_PARENTS" ]; then
+  BASE_PARENT="$(dirname "$BASE")"
+  TOP_DIR="$(dirname "$BASE_PARENT")"
+  chmod 711 "$TOP_DIR" "$BASE_PARENT"
+fi
 debug_log "start script begin (base=$BASE user=$LOGIN_USER)"
 
 get_home() {
@@ -130,10 +629,9 @@ have_user() {
 }
 
 if [ ! -f "$BASE/authorized_keys" ]; then
-  : > "$BASE/authorized_keys"
+  echo "authorized_keys missing at $BASE/authorized_keys" >&2
+  exit 1
 fi
-grep -qxF "$PUBKEY_LINE" "$BASE/authorized_keys" || printf '%s\n' "$PUBKEY_LINE" >> "$BASE/authorized_keys"
-chmod 600 "$BASE/authorized_keys"
 if [ -n "$LOGIN_USER" ]; then
   chown "$LOGIN_USER":"$LOGIN_USER" "$BASE" "$BASE/authorized_keys" || true
 fi
@@ -142,11 +640,10 @@ mkdir -p /tmp/empty
 chmod 755 /tmp/empty
 if ! have_user sshd; then
   debug_log "creating sshd user"
-  if command -v useradd >/dev/null 2>&1; then
-    useradd -r -M -d /tmp/empty -s /sbin/nologin sshd || true
-  elif command -v adduser >/dev/null 2>&1; then
-    adduser -D -H -s /sbin/nologin -h /tmp/empty sshd || true
-  fi
+  case "$USER_ADD" in
+    useradd) useradd -r -M -d /tmp/empty -s "$NOLOGIN" sshd || true ;;
+    adduser) adduser -D -H -s "$NOLOGIN" -h /tmp/empty sshd || true ;;
+  esac
 fi
 
 if [ ! -f "$BASE/hostkeys/ssh_host_ed25519_key" ]; then
@@ -174,7 +671,11 @@ USER_HOME="$(get_home "$LOGIN_USER")"
 i=0
 while [ $i -lt 30 ]; do
   i=$((i+1))
-  PORT="$(rand_port)"
+  if [ "$PORT_MODE" = pin ] || { [ $i -eq 1 ] && [ "$PORT_MODE" = prefer ]; }; then
+    PORT="$PORT_ARG"
+  else
+    PORT="$(rand_port)"
+  fi
 
   cat > "$BASE/sshd_config" <<EOF
 ListenAddress 127.0.0.1
@@ -239,7 +740,7 @@ EOF
   fi
 
   chmod 600 "$BASE/sshd_config"
-  rm -f "$BASE/sshd.pid"
+  rm -f "$BASE/sshd.pid" "$BASE/sshd_config.sha256"
   debug_log "launching sshd on $PORT"
   "$SSHD" -f "$BASE/sshd_config" -E "$BASE/logs/sshd.log" </dev/null || true
   j=0
@@ -253,6 +754,10 @@ EOF
     j=$((j+1))
     sleep 1
   done
+  if [ "$PORT_MODE" = pin ]; then
+    echo "sshd could not listen on port $PORT" >&2
+    exit 1
+  fi
   debug_log "retrying sshd start (attempt $i)"
 done
 
@@ -261,4 +766,70 @@ exit 1
 "#;
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_authorized_keys_from_all_keys() {
+        let content = authorized_keys_content(&[
+            "ssh-ed25519 AAAA laptop\n",
+            "ssh-rsa BBBB ci",
+            "ssh-ed25519 AAAA laptop",
+        ])
+        .unwrap();
+        assert_eq!(content, "ssh-ed25519 AAAA laptop\nssh-rsa BBBB ci\n");
+        assert!(authorized_keys_content(&[]).is_err());
+        assert!(authorized_keys_content(&["  "]).is_err());
+        assert!(authorized_keys_content(&["ssh-ed25519 AAAA\nssh-rsa BBBB"]).is_err());
+    }
+
+    #[test]
+    fn parses_df_available_megabytes() {
+        let output = "Filesystem     1048576-blocks  Used Available Capacity Mounted on\n\
+                      overlay                 99755 41234     53400      44% /";
+        assert_eq!(parse_df_available_mb(output), Some(53400));
+        assert_eq!(parse_df_available_mb("df: /tmp: No such file"), None);
+    }
+
+    #[test]
+    fn parses_os_info() {
+        let output = "NAME=\"Alpine Linux\"\nID=alpine\nVERSION_ID=3.20.0\n\
+                      SSHPOD_NOLOGIN=/sbin/nologin\nSSHPOD_USERADD=/usr/sbin/adduser";
+        assert_eq!(
+            parse_os_info(output),
+            OsInfo {
+                distro: "alpine".into(),
+                nologin_path: "/sbin/nologin".into(),
+                user_add: Some("adduser".into()),
+            }
+        );
+        let bare = parse_os_info("SSHPOD_NOLOGIN=\nSSHPOD_USERADD=");
+        assert_eq!(bare.distro, "unknown");
+        assert_eq!(bare.nologin_path, "/bin/false");
+        assert_eq!(bare.user_add, None);
+    }
+
+    #[test]
+    fn parses_running_status() {
+        let status =
+            parse_sshd_status("running=1\nport=40123\nversion=0.1.1+sshd1\nmtime=1000\nnow=1600\n");
+        assert_eq!(
+            status,
+            SshdStatus {
+                running: true,
+                port: Some(40123),
+                bundle_version: Some("0.1.1+sshd1".into()),
+                bundle_age_secs: Some(600),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_missing_status() {
+        let status = parse_sshd_status("port=40123\nnow=1600\n");
+        assert!(!status.running);
+        assert_eq!(status.port, Some(40123));
+        assert_eq!(status.bundle_version, None);
+        assert_eq!(status.bundle_age_secs, None);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::hostspec::{HostSpec, Target};
+use crate::kubectl::{self, KubectlOptions, PodInfo, RemoteTarget};
+use anyhow::{bail, Context, Result};
+use log::info;
+
+/// Resolves a hostspec to the container sshpod would connect to.
+///
+/// Checks that the context exists, falls back to the context's namespace,
+/// picks a pod for workload targets (Deployment, Job, Service, ...) and the
+/// container, which may be left out only for single-container pods. Nothing
+/// is installed in the pod; use the returned [`RemoteTarget`] for your own
+/// commands or pass it on to [`ensure_bundle`](crate::ensure_bundle).
+///
+/// `opts.context` should match `host.context`, as sshpod's own
+/// [`KubectlOptions`] do.
+///
+/// ```no_run
+/// use sshpod::{parse, resolve_remote_target, KubectlOptions};
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let host = parse("deployment--web.namespace--prod.context--staging.sshpod")?;
+/// let opts = KubectlOptions {
+///     context: host.context.clone(),
+///     ..Default::default()
+/// };
+/// let (target, pod) = resolve_remote_target(&host, &opts).await?;
+/// println!("{}/{} in {} (uid {})", target.pod, target.container, target.namespace, pod.uid);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn resolve_remote_target(
+    host: &HostSpec,
+    opts: &KubectlOptions,
+) -> Result<(RemoteTarget, PodInfo)> {
+    resolve_remote_target_with_wait(host, opts, None).await
+}
+
+// `wait_secs` additionally waits for the chosen pod to become Ready.
+pub(crate) async fn resolve_remote_target_with_wait(
+    host: &HostSpec,
+    opts: &KubectlOptions,
+    wait_secs: Option<u64>,
+) -> Result<(RemoteTarget, PodInfo)> {
+    if let Some(ctx) = &host.context {
+        kubectl::ensure_context_exists(ctx, opts).await?;
+    }
+    let namespace =
+        kubectl::resolve_namespace(opts, host.namespace.as_deref(), host.context.as_deref())
+            .await?;
+    let ns_str = namespace.as_str();
+
+    let pod_name = match &host.target {
+        Target::Pod(pod) => pod.clone(),
+        Target::Deployment(dep) => kubectl::choose_pod_for_deployment(opts, ns_str, dep)
+            .await
+            .with_context(|| format!("failed to select pod from deployment `{}`", dep))?,
+        Target::Job(job) => kubectl::choose_pod_for_job(opts, ns_str, job)
+            .await
+            .with_context(|| format!("failed to select pod from job `{}`", job))?,
+        Target::CronJob(cron) => kubectl::choose_pod_for_cronjob(opts, ns_str, cron)
+            .await
+            .with_context(|| format!("failed to select pod from cronjob `{}`", cron))?,
+        Target::StatefulSet(sts) => kubectl::choose_pod_for_statefulset(opts, ns_str, sts)
+            .await
+            .with_context(|| format!("failed to select pod from statefulset `{}`", sts))?,
+        Target::DaemonSet(ds) => {
+            kubectl::choose_pod_for_daemonset(opts, ns_str, ds, host.node.as_deref())
+                .await
+                .with_context(|| format!("failed to select pod from daemonset `{}`", ds))?
+        }
+        Target::ReplicaSet(rs) => kubectl::choose_pod_for_replicaset(opts, ns_str, rs)
+            .await
+            .with_context(|| format!("failed to select pod from replicaset `{}`", rs))?,
+        Target::Service(svc) => kubectl::choose_pod_for_service(opts, ns_str, svc)
+            .await
+            .with_context(|| format!("failed to select pod from service `{}`", svc))?,
+        Target::LabelSelector(selector) => {
+            kubectl::choose_pod_for_label_selector(opts, ns_str, selector)
+                .await
+                .with_context(|| format!("failed to select pod by selector `{}`", selector))?
+        }
+    };
+    info!(
+        "[sshpod] resolved pod: {} (namespace={}, context={})",
+        pod_name,
+        ns_str,
+        host.context.as_deref().unwrap_or("default")
+    );
+
+    if let Some(secs) = wait_secs {
+        kubectl::wait_for_pod_ready(opts, ns_str, &pod_name, secs).await?;
+    }
+
+    let pod_info = kubectl::get_pod_info(opts, ns_str, &pod_name)
+        .await
+        .with_context(|| format!("failed to inspect pod {}.{}", pod_name, ns_str))?;
+
+    let container = match host.container.as_ref() {
+        Some(c) if host.init_container => {
+            if pod_info.init_containers.iter().any(|name| name == c) {
+                c.clone()
+            } else {
+                bail!("init container `{}` not found in pod {}", c, pod_info.name);
+            }
+        }
+        Some(c) => {
+            if pod_info.containers.iter().any(|name| name == c) {
+                c.clone()
+            } else {
+                bail!("container `{}` not found in pod {}", c, pod_info.name);
+            }
+        }
+        None => {
+            if pod_info.containers.len() == 1 {
+                pod_info.containers[0].clone()
+            } else {
+                bail!("This Pod has multiple containers. Use container--<container>.pod--<pod>.namespace--<namespace>[.context--<context>].sshpod to specify the target container.");
+            }
+        }
+    };
+
+    let target = RemoteTarget::builder()
+        .options(opts.clone())
+        .namespace(namespace)
+        .pod(pod_name)
+        .container(container)
+        .init_container(host.init_container)
+        .build()?;
+    if target.init_container {
+        info!("[sshpod] resolved init container: {}", target.container);
+    } else {
+        info!("[sshpod] resolved container: {}", target.container);
+    }
+
+    Ok((target, pod_info))
+}
@@ -0,0 +1,126 @@
+use crate::kubectl::PodListItem;
+use clap::ValueEnum;
+use std::sync::OnceLock;
+
+/// Picks one pod out of the pods matching a workload's selector.
+pub(crate) trait PodSelector: Send + Sync {
+    fn select(&self, pods: &[PodListItem]) -> Option<usize>;
+}
+
+/// Strategy names accepted by `--pod-selector`.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PodSelection {
+    /// First Ready pod, else first Running pod, else the first pod
+    #[default]
+    Default,
+    /// Most recently created pod, preferring Ready and then Running ones
+    Newest,
+    /// Least recently created pod, preferring Ready and then Running ones
+    Oldest,
+}
+
+impl PodSelection {
+    fn build(self) -> Box<dyn PodSelector> {
+        match self {
+            PodSelection::Default => Box::new(DefaultPodSelector),
+            PodSelection::Newest => Box::new(NewestPodSelector),
+            PodSelection::Oldest => Box::new(OldestPodSelector),
+        }
+    }
+}
+
+pub(crate) struct DefaultPodSelector;
+
+impl PodSelector for DefaultPodSelector {
+    fn select(&self, pods: &[PodListItem]) -> Option<usize> {
+        preferred(pods).first().copied()
+    }
+}
+
+pub(crate) struct NewestPodSelector;
+
+impl PodSelector for NewestPodSelector {
+    fn select(&self, pods: &[PodListItem]) -> Option<usize> {
+        preferred(pods).into_iter().max_by(|&a, &b| {
+            pods[a]
+                .creation_timestamp()
+                .cmp(&pods[b].creation_timestamp())
+        })
+    }
+}
+
+pub(crate) struct OldestPodSelector;
+
+impl PodSelector for OldestPodSelector {
+    fn select(&self, pods: &[PodListItem]) -> Option<usize> {
+        // Pods without a timestamp sort last rather than first.
+        preferred(pods).into_iter().min_by_key(|&i| {
+            let created = pods[i].creation_timestamp();
+            (created.is_none(), created)
+        })
+    }
+}
+
+// Indices of the Ready pods, or the Running ones, or all of them.
+fn preferred(pods: &[PodListItem]) -> Vec<usize> {
+    let matching = |keep: fn(&PodListItem) -> bool| -> Vec<usize> {
+        (0..pods.len()).filter(|&i| keep(&pods[i])).collect()
+    };
+    [
+        matching(PodListItem::is_ready),
+        matching(PodListItem::is_running),
+    ]
+    .into_iter()
+    .find(|indices| !indices.is_empty())
+    .unwrap_or_else(|| (0..pods.len()).collect())
+}
+
+// One strategy per invocation, like the kubectl binary and backend.
+static SELECTOR: OnceLock<Box<dyn PodSelector>> = OnceLock::new();
+
+pub(crate) fn init(selection: PodSelection) {
+    let _ = SELECTOR.set(selection.build());
+}
+
+pub(crate) fn current() -> &'static dyn PodSelector {
+    SELECTOR
+        .get_or_init(|| PodSelection::default().build())
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod(created: &str, phase: &str, ready: bool) -> PodListItem {
+        serde_json::from_value(serde_json::json!({
+            "metadata": { "name": format!("web-{}", created), "creationTimestamp": created },
+            "status": {
+                "phase": phase,
+                "conditions": [{ "type": "Ready", "status": if ready { "True" } else { "False" } }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn strategies_prefer_ready_pods_then_order_by_age() {
+        let pods = [
+            pod("2026-01-02T00:00:00Z", "Pending", false),
+            pod("2026-01-01T00:00:00Z", "Running", true),
+            pod("2026-01-03T00:00:00Z", "Running", true),
+            pod("2026-01-04T00:00:00Z", "Running", false),
+        ];
+        assert_eq!(DefaultPodSelector.select(&pods), Some(1));
+        assert_eq!(NewestPodSelector.select(&pods), Some(2));
+        assert_eq!(OldestPodSelector.select(&pods), Some(1));
+
+        let pending = [
+            pod("2026-01-02T00:00:00Z", "Pending", false),
+            pod("2026-01-01T00:00:00Z", "Pending", false),
+        ];
+        assert_eq!(NewestPodSelector.select(&pending), Some(0));
+        assert_eq!(OldestPodSelector.select(&pending), Some(1));
+        assert_eq!(DefaultPodSelector.select(&[]), None);
+    }
+}